@@ -0,0 +1,318 @@
+use super::*;
+
+/// The default number of words in a shingle.
+const SHINGLE_SIZE_DEFAULT: usize = 3;
+/// The default number of hash functions in a MinHash signature.
+const MINHASH_PERMUTATIONS_DEFAULT: usize = 128;
+/// The default similarity at or above which two texts are near-duplicates.
+const DEDUP_THRESHOLD_DEFAULT: f32 = 0.8;
+/// The number of bits in a SimHash fingerprint.
+const SIMHASH_BITS: u32 = u64::BITS;
+
+/// How texts are fingerprinted for near-duplicate detection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FingerprintMethod {
+    /// A signature of the minimum hash of the text's shingles under `permutations` hash functions. Similarity estimates the Jaccard similarity of the shingle sets.
+    /// More permutations give a more accurate estimate, at the cost of a larger signature.
+    MinHash { permutations: usize },
+    /// A single 64-bit fingerprint where each bit is the majority vote of the text's shingle hashes. Similarity is the fraction of matching bits.
+    /// Cheaper than MinHash, but less precise for short texts.
+    SimHash,
+}
+
+impl Default for FingerprintMethod {
+    fn default() -> Self {
+        Self::MinHash {
+            permutations: MINHASH_PERMUTATIONS_DEFAULT,
+        }
+    }
+}
+
+/// The fingerprint of a text, created by [`Deduplicator::fingerprint`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fingerprint {
+    MinHash(Vec<u64>),
+    SimHash(u64),
+}
+
+impl Fingerprint {
+    /// The estimated similarity of two texts from 0.0 to 1.0. Fingerprints from different methods or permutation counts have a similarity of 0.0.
+    pub fn similarity(&self, other: &Fingerprint) -> f32 {
+        match (self, other) {
+            (Self::MinHash(a), Self::MinHash(b)) if a.len() == b.len() && !a.is_empty() => {
+                a.iter().zip(b).filter(|(a, b)| a == b).count() as f32 / a.len() as f32
+            }
+            (Self::SimHash(a), Self::SimHash(b)) => {
+                1.0 - (a ^ b).count_ones() as f32 / SIMHASH_BITS as f32
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Which texts are near-duplicates, created by [`Deduplicator::deduplicate`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DedupReport {
+    /// Each group of near-duplicates, as indices into the texts. The first index of a cluster is the text that is kept, and every text in a cluster is a near-duplicate of it.
+    /// Texts without a near-duplicate are not in any cluster.
+    pub clusters: Vec<Vec<usize>>,
+    /// The indices of the texts to drop, in ascending order: every text in a cluster except the first.
+    pub dropped: Vec<usize>,
+}
+
+impl DedupReport {
+    /// Whether the text at `index` should be dropped.
+    pub fn is_dropped(&self, index: usize) -> bool {
+        self.dropped.binary_search(&index).is_ok()
+    }
+}
+
+/// Finds near-duplicate chunks, such as boilerplate footers and mirrored pages, so they can be dropped before embedding.
+/// Texts are split into words with [`TextSplitter::on_words_unicode`], normalized to lowercase, and fingerprinted over shingles of consecutive words.
+///
+/// ```ignore
+/// let report = Deduplicator::new().threshold(0.9).deduplicate_result(&result);
+/// for (i, chunk) in result.chunks_to_text().into_iter().enumerate() {
+///     if !report.is_dropped(i) {
+///         // embed the chunk
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Deduplicator {
+    /// Default is [`FingerprintMethod::MinHash`] with 128 permutations.
+    method: FingerprintMethod,
+    /// The number of consecutive words in a shingle. Never less than 1.
+    shingle_size: usize,
+    /// From 0.0 to 1.0.
+    threshold: f32,
+}
+
+impl Default for Deduplicator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self {
+            method: FingerprintMethod::default(),
+            shingle_size: SHINGLE_SIZE_DEFAULT,
+            threshold: DEDUP_THRESHOLD_DEFAULT,
+        }
+    }
+
+    /// Fingerprints texts with MinHash. This is the default.
+    ///
+    /// * `permutations` - The number of hash functions in the signature. Default is 128. Values of 0 are coerced to 1.
+    pub fn minhash(mut self, permutations: usize) -> Self {
+        self.method = FingerprintMethod::MinHash {
+            permutations: permutations.max(1),
+        };
+        self
+    }
+
+    /// Fingerprints texts with SimHash.
+    pub fn simhash(mut self) -> Self {
+        self.method = FingerprintMethod::SimHash;
+        self
+    }
+
+    /// Sets the number of consecutive words in a shingle. Larger shingles are stricter about word order. Texts with fewer words are one shingle.
+    ///
+    /// * `shingle_size` - Default is 3. Values of 0 are coerced to 1.
+    pub fn shingle_size(mut self, shingle_size: usize) -> Self {
+        self.shingle_size = shingle_size.max(1);
+        self
+    }
+
+    /// Sets the similarity at or above which two texts are near-duplicates.
+    ///
+    /// * `threshold` - From 0.0 to 1.0. Default is 0.8. Values outside the range are clamped.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Fingerprints a text with the configured [`FingerprintMethod`].
+    pub fn fingerprint(&self, text: &str) -> Fingerprint {
+        let shingles = self.shingle_hashes(text);
+        match self.method {
+            // A variant built outside of `Deduplicator::minhash` can have 0 permutations, which would make every fingerprint empty.
+            FingerprintMethod::MinHash { permutations } => Fingerprint::MinHash(
+                (0..permutations.max(1) as u64)
+                    .map(|seed| {
+                        shingles
+                            .iter()
+                            .map(|shingle| mix(shingle ^ mix(seed)))
+                            .min()
+                            .unwrap_or(u64::MAX)
+                    })
+                    .collect(),
+            ),
+            FingerprintMethod::SimHash => {
+                let mut votes = [0i64; SIMHASH_BITS as usize];
+                for shingle in &shingles {
+                    for (bit, vote) in votes.iter_mut().enumerate() {
+                        *vote += if shingle >> bit & 1 == 1 { 1 } else { -1 };
+                    }
+                }
+                Fingerprint::SimHash(
+                    votes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, vote)| **vote > 0)
+                        .fold(0, |fingerprint, (bit, _)| fingerprint | 1 << bit),
+                )
+            }
+        }
+    }
+
+    /// Clusters near-duplicate texts. Each text joins the cluster of the first earlier kept text it is a near-duplicate of, so the earliest text of each cluster is kept.
+    /// Every kept text is compared with every later text, so this is quadratic in the number of texts.
+    pub fn deduplicate<T: AsRef<str> + Sync>(&self, texts: &[T]) -> DedupReport {
+        let fingerprints: Vec<Fingerprint> = texts
+            .par_iter()
+            .map(|text| self.fingerprint(text.as_ref()))
+            .collect();
+        self.deduplicate_fingerprints(&fingerprints)
+    }
+
+    /// Clusters near-duplicate fingerprints, such as fingerprints computed once and stored with each chunk. See [`Deduplicator::deduplicate`].
+    pub fn deduplicate_fingerprints(&self, fingerprints: &[Fingerprint]) -> DedupReport {
+        let mut clustered = vec![false; fingerprints.len()];
+        let mut report = DedupReport::default();
+        for i in 0..fingerprints.len() {
+            if clustered[i] {
+                continue;
+            }
+            let mut cluster = vec![i];
+            for j in i + 1..fingerprints.len() {
+                if !clustered[j] && fingerprints[i].similarity(&fingerprints[j]) >= self.threshold {
+                    clustered[j] = true;
+                    cluster.push(j);
+                }
+            }
+            if cluster.len() > 1 {
+                report.dropped.extend(&cluster[1..]);
+                report.clusters.push(cluster);
+            }
+        }
+        report.dropped.sort_unstable();
+        report
+    }
+
+    /// Clusters the near-duplicate chunks of a [`ChunkerResult`]. Indices are positions in [`ChunkerResult::chunks_to_text`].
+    pub fn deduplicate_result(&self, result: &ChunkerResult) -> DedupReport {
+        let texts: Vec<String> = result.chunks.iter().map(Chunk::joined_text).collect();
+        self.deduplicate(&texts)
+    }
+
+    /// The hash of each shingle of consecutive normalized words in `text`.
+    fn shingle_hashes(&self, text: &str) -> Vec<u64> {
+        let words: Vec<String> = match TextSplitter::new()
+            .on_words_unicode()
+            .recursive(false)
+            .split_text(text)
+        {
+            Some(splits) => splits.iter().filter_map(|split| normalize(split.text())).collect(),
+            None => normalize(text).into_iter().collect(),
+        };
+        if words.is_empty() {
+            return Vec::new();
+        }
+        words
+            .windows(self.shingle_size.clamp(1, words.len()))
+            .map(|shingle| {
+                shingle.iter().fold(FNV_OFFSET_BASIS, |hash, word| {
+                    // Hash each word followed by a separator, so shingles of different words never collide by concatenation.
+                    fnv1a(fnv1a(hash, word.as_bytes()), &[0])
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lowercases a word and trims the punctuation and whitespace around it. None if nothing is left.
+fn normalize(word: &str) -> Option<String> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+    (!word.is_empty()).then(|| word.to_lowercase())
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a, used rather than [`std::hash::DefaultHasher`] so fingerprints are stable across Rust versions and can be stored.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// The SplitMix64 finalizer, which derives the independent hash functions of a MinHash signature from one shingle hash.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn near_duplicates() {
+        let footer = "Copyright 2024 Example Corp. All rights reserved. Contact us at support@example.com for help with your account, billing, or anything else. Our offices are open Monday through Friday, and we usually answer every message within two business days.";
+        let texts = [
+            footer.to_string(),
+            "The quick brown fox jumps over the lazy dog while the cat sleeps in the warm afternoon sun.".to_string(),
+            footer.replace("2024", "2025"),
+            footer.to_uppercase(),
+        ];
+        for deduplicator in [Deduplicator::new(), Deduplicator::new().simhash()] {
+            let report = deduplicator.deduplicate(&texts);
+            assert_eq!(report.clusters, vec![vec![0, 2, 3]]);
+            assert_eq!(report.dropped, vec![2, 3]);
+            assert!(report.is_dropped(2));
+            assert!(!report.is_dropped(1));
+
+            let a = deduplicator.fingerprint(&texts[0]);
+            assert_eq!(a.similarity(&deduplicator.fingerprint(&texts[3])), 1.0);
+            assert!(a.similarity(&deduplicator.fingerprint(&texts[1])) < 0.8);
+        }
+        // A threshold of 1.0 only clusters texts with identical fingerprints.
+        let report = Deduplicator::new().threshold(1.0).deduplicate(&texts);
+        assert_eq!(report.clusters, vec![vec![0, 3]]);
+
+        // Zero permutations and shingle sizes are coerced to 1.
+        let deduplicator = Deduplicator::new().minhash(0).shingle_size(0);
+        assert_eq!(
+            deduplicator.fingerprint(&texts[0]),
+            deduplicator.fingerprint(&texts[3])
+        );
+        assert!(
+            matches!(deduplicator.fingerprint(&texts[0]), Fingerprint::MinHash(signature) if signature.len() == 1)
+        );
+    }
+
+    #[test]
+    fn chunker_result() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .run_return_result(content)
+            .unwrap();
+        let chunks = res.chunks_to_text();
+        let deduplicator = Deduplicator::new().threshold(0.5);
+        let report = deduplicator.deduplicate_result(&res);
+        assert_eq!(report, deduplicator.deduplicate(&chunks));
+        for cluster in &report.clusters {
+            assert!(cluster.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(!report.is_dropped(cluster[0]));
+        }
+    }
+}
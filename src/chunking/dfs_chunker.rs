@@ -13,17 +13,17 @@ pub struct DfsTextChunker {
 
 impl DfsTextChunker {
     /// This is called from [`TextChunker`], but you can call it by creating a [`ChunkerConfig`] and passing it in.
-    pub fn run(config: &Arc<ChunkerConfig>) -> Option<Vec<Chunk>> {
+    pub fn run(config: &Arc<ChunkerConfig>) -> Result<Vec<Chunk>, ChunkerError> {
         let splits = config.initial_splits.clone();
 
         if splits
             .iter()
             .any(|split: &TextSplit| split.token_count.unwrap() as f32 > config.length_max)
         {
-            eprintln!(
-                "\nPure semantic chunking is impossible for separator: {:#?}.\nA splits token count is more than length_max: {:#?}.", config.initial_separator, config.length_max,
-            );
-            return None;
+            return Err(config.impossible(format!(
+                "pure semantic chunking is impossible, a split's token count is more than length_max: {}",
+                config.length_max
+            )));
         };
         let mut chunker = DfsTextChunker {
             splits,
@@ -34,8 +34,12 @@ impl DfsTextChunker {
 
         chunker.remaining_token_count = chunker.config.estimate_splits_token_count(&chunker.splits);
 
-        let chunk_split_indexes = chunker.find_valid_chunk_combinations()?;
-        chunker.create_chunks(chunk_split_indexes)
+        let chunk_split_indexes = chunker.find_valid_chunk_combinations();
+        chunker.config.chunks_found.check()?;
+        let chunk_split_indexes = chunk_split_indexes.ok_or_else(|| {
+            config.impossible("no combination of splits forms chunks within min and max")
+        })?;
+        Ok(chunker.create_chunks(chunk_split_indexes))
     }
 
     /// Runs the recursive chunk combo finding process.
@@ -52,7 +56,7 @@ impl DfsTextChunker {
     /// It then recursively calls itself with each of those splits as the new starting split.
     /// It does this until it finds a path that reaches the final split.
     fn recursive_chunk_tester(&mut self, start: usize) -> Option<Vec<usize>> {
-        if self.config.chunks_found.load(Ordering::Relaxed) {
+        if self.config.chunks_found.load() {
             return None;
         }
         if self.valid_split_indices_memo.contains_key(&{ start }) {
@@ -88,7 +92,7 @@ impl DfsTextChunker {
             chunk.add_split(split.clone(), false);
 
             if chunk.estimated_token_count >= self.config.absolute_length_min as f32 {
-                if chunk.estimated_token_count > self.config.length_max {
+                if chunk.token_count(true) > self.config.length_max {
                     break;
                 }
                 valid_split_indices.push(index);
@@ -107,7 +111,7 @@ impl DfsTextChunker {
     }
 
     /// Creates chunks from a list of split indices.
-    fn create_chunks(&self, chunk_split_indexes: Vec<usize>) -> Option<Vec<Chunk>> {
+    fn create_chunks(&self, chunk_split_indexes: Vec<usize>) -> Vec<Chunk> {
         let mut chunks = Vec::new();
         let mut chunk_ranges: Vec<(usize, usize)> = Vec::new();
         chunk_ranges.push((0, chunk_split_indexes[0]));
@@ -125,7 +129,7 @@ impl DfsTextChunker {
 
             chunks.push(chunk);
         }
-        Some(chunks)
+        chunks
     }
 }
 
@@ -137,8 +141,8 @@ mod tests {
     fn runner(
         tokenizer: &std::sync::Arc<LlmTokenizer>,
         separator: Separator,
-    ) -> Option<Vec<Chunk>> {
-        let chunks_found: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    ) -> Result<Vec<Chunk>, ChunkerError> {
+        let chunks_found = ChunksFound::new(true);
         let incoming_text =
             "\n\nOne one one one.\n\nTwo two two two.\n\n\nThree three three three.\n\n";
         let absolute_length_max = 5;
@@ -148,8 +152,11 @@ mod tests {
             incoming_text,
             absolute_length_max,
             None,
-            Some(0.0),
-            Arc::clone(tokenizer),
+            Some(OverlapStrategy::Percent(0.0)),
+            TextMode::Cleaned,
+            None,
+            None,
+            TokenCounter::new(tokenizer.clone()),
         )?);
 
         DfsTextChunker::run(&config)
@@ -0,0 +1,36 @@
+use crate::splitting::Separator;
+
+/// Why the [`super::TextChunker`] failed to chunk a text.
+#[derive(Debug, thiserror::Error)]
+pub enum ChunkerError {
+    /// The chunker was configured with settings that can never produce valid chunks.
+    #[error("invalid chunker config: {0}")]
+    InvalidConfig(String),
+    /// The incoming text has no content to chunk.
+    #[error("text is too short to chunk: {0}")]
+    TextTooShort(String),
+    /// Valid chunks could not be built when starting from `separator`.
+    #[error("chunking is impossible for separator {separator:?}: {reason}")]
+    ImpossibleForSeparator { separator: Separator, reason: String },
+    /// No separator produced valid chunks. `failures` holds the reason each attempt failed, in separator priority order.
+    #[error("chunking failed for every separator: [{}]", .failures.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    AllSeparatorsFailed { failures: Vec<ChunkerError> },
+    /// The attempt was stopped before it finished, because chunks from another attempt are used instead.
+    #[error("chunking was cancelled")]
+    Cancelled,
+    /// The caller cancelled chunking with the [`super::CancellationToken`] passed to [`super::TextChunker::cancellation_token`].
+    #[error("chunking was cancelled by the caller")]
+    CancelledByCaller,
+    /// Chunking ran longer than the budget set with [`super::TextChunker::time_budget`].
+    #[error("chunking exceeded its time budget")]
+    TimedOut,
+    /// The tokenizer could not be loaded.
+    #[error("tokenizer error: {0}")]
+    Tokenizer(#[source] anyhow::Error),
+    /// The [`super::Embedder`] failed to embed the text.
+    #[error("embedder error: {0}")]
+    Embedder(#[source] anyhow::Error),
+    /// Reading the incoming text failed.
+    #[error("failed to read text: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,167 @@
+use super::*;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::io::Write;
+
+/// A chunk with its metadata, flattened for persistence, such as loading into a vector store. Created by [`ChunkerResult::records`].
+/// All ranges are byte ranges in the incoming text.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChunkRecord {
+    /// The id of the document the chunk came from, if one was given.
+    pub doc_id: Option<String>,
+    /// The position of the chunk within its document.
+    pub index: usize,
+    pub text: String,
+    /// The source of the chunk, including any overlap.
+    pub source_range: Range<usize>,
+    /// The source of the chunk, excluding any overlap. The core ranges of a document's chunks never overlap.
+    pub core_source_range: Range<usize>,
+    /// The overlap taken from the previous chunk, if any.
+    pub overlap_before: Option<Range<usize>>,
+    /// The overlap taken from the next chunk, if any.
+    pub overlap_after: Option<Range<usize>>,
+    /// The token count of the chunk for each sizer, with the [`TextChunker`]'s own sizer first.
+    pub token_counts: Vec<u32>,
+    /// The [`Separator`] the chunks were split on.
+    pub separator: Separator,
+    /// The heading path of the chunk. See [`ChunkerResult::heading_paths`].
+    pub heading_path: Vec<String>,
+}
+
+impl ChunkerResult {
+    /// The chunks as [`ChunkRecord`]s, ready to serialize.
+    ///
+    /// * `doc_id` - The id of the document, recorded in each record.
+    pub fn records(&self, doc_id: Option<&str>) -> Vec<ChunkRecord> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let text = chunk.joined_text();
+                let source_range = chunk.source_range();
+                let core_source_range = chunk.core_source_range();
+                ChunkRecord {
+                    doc_id: doc_id.map(str::to_string),
+                    index,
+                    token_counts: self.sizers.iter().map(|sizer| sizer.size(&text)).collect(),
+                    text,
+                    overlap_before: (source_range.start < core_source_range.start)
+                        .then_some(source_range.start..core_source_range.start),
+                    overlap_after: (core_source_range.end < source_range.end)
+                        .then_some(core_source_range.end..source_range.end),
+                    source_range,
+                    core_source_range,
+                    separator: self.initial_separator.clone(),
+                    heading_path: self.heading_paths.get(index).cloned().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Serializes the separator and the chunks as [`ChunkRecord`]s without a document id.
+impl Serialize for ChunkerResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ChunkerResult", 2)?;
+        state.serialize_field("separator", &self.initial_separator)?;
+        state.serialize_field("chunks", &self.records(None))?;
+        state.end()
+    }
+}
+
+/// Serializes the chunk's text and its ranges in the base text and incoming text.
+impl Serialize for Chunk {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Chunk", 4)?;
+        state.serialize_field("text", &self.joined_text())?;
+        state.serialize_field("base_text_range", &self.base_text_range())?;
+        state.serialize_field("source_range", &self.source_range())?;
+        state.serialize_field("core_source_range", &self.core_source_range())?;
+        state.end()
+    }
+}
+
+/// Writes chunks as JSON Lines, one [`ChunkRecord`] per line. Wrap files in a [`std::io::BufWriter`].
+///
+/// ```ignore
+/// let mut writer = JsonlWriter::new(BufWriter::new(File::create("chunks.jsonl")?));
+/// for (doc_id, result) in chunker.run_batch_return_result(documents) {
+///     writer.write_result(Some(doc_id), &result?)?;
+/// }
+/// ```
+pub struct JsonlWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonlWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes every chunk of `result`.
+    ///
+    /// * `doc_id` - The id of the document, recorded in each line.
+    pub fn write_result(
+        &mut self,
+        doc_id: Option<&str>,
+        result: &ChunkerResult,
+    ) -> std::io::Result<()> {
+        for record in result.records(doc_id) {
+            self.write_record(&record)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_record(&mut self, record: &ChunkRecord) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn jsonl() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .overlap(OverlapStrategy::Tokens(16))
+            .run_return_result(content)
+            .unwrap();
+        let mut writer = JsonlWriter::new(Vec::new());
+        writer.write_result(Some("doc-1"), &res).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        let records: Vec<ChunkRecord> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let chunks = res.chunks_to_text();
+        assert_eq!(records.len(), chunks.len());
+        assert_eq!(records, res.records(Some("doc-1")));
+        for (i, (record, chunk)) in records.iter().zip(&chunks).enumerate() {
+            assert_eq!(record.doc_id.as_deref(), Some("doc-1"));
+            assert_eq!(record.index, i);
+            assert_eq!(&record.text, chunk);
+            assert_eq!(record.token_counts, vec![res.token_counts()[i]]);
+            assert_eq!(&record.separator, &res.initial_separator);
+        }
+        assert!(records.iter().any(|record| record.overlap_before.is_some()));
+        assert!(records.iter().any(|record| record.overlap_after.is_some()));
+
+        let value = serde_json::to_value(&res).unwrap();
+        assert_eq!(value["chunks"].as_array().unwrap().len(), chunks.len());
+    }
+}
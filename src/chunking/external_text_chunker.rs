@@ -1,6 +1,6 @@
 use std::{
     collections::VecDeque,
-    sync::{atomic::AtomicBool, Arc},
+    sync::Arc,
 };
 
 use llm_models::tokenizer::LlmTokenizer;
@@ -8,9 +8,12 @@ use text_splitter::ChunkConfig;
 use text_splitter::TextSplitter as TextSplitterExternal;
 use tiktoken_rs::cl100k_base;
 
-use crate::splitting::Separator;
+use crate::{clean_text::OffsetMap, splitting::Separator};
 
-use super::{Chunk, ChunkerConfig, ChunkerResult, ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO};
+use super::{
+    report::TokenCounter, Chunk, ChunkSizer, ChunkerConfig, ChunkerResult, ChunksFound,
+    OverlapStrategy, PhaseDurations, TextMode, ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO,
+};
 
 /// Chunk incoming text using the [text-splitter](https://github.com/benbrandt/text-splitter) crate.
 /// This is a dev-dependency for comparing the performance of the text-splitter crate with the TextChunker.
@@ -25,7 +28,8 @@ pub fn chunk_text_with_text_splitter(
     let config = if let Some(overlap_percent) = overlap_percent {
         let overlap = (max_chunk_token_size as f32 * overlap_percent).floor() as u32;
         if overlap >= max_chunk_token_size {
-            eprintln!("Overlap is greater than or equal to max_chunk_token_size");
+            #[cfg(feature = "tracing")]
+            tracing::warn!("chunk_text_with_text_splitter: overlap is greater than or equal to max_chunk_token_size.");
             return None;
         }
         let max_chunk_token_size = max_chunk_token_size - overlap;
@@ -48,16 +52,23 @@ pub fn chunk_text_with_text_splitter(
     let tokenizer = LlmTokenizer::new_tiktoken("gpt-4").unwrap();
 
     let dummy_config = Arc::new(ChunkerConfig {
-        chunks_found: Arc::new(AtomicBool::new(true)),
+        chunks_found: ChunksFound::new(true),
         absolute_length_max: max_chunk_token_size,
         length_max: max_chunk_token_size as f32,
         absolute_length_min: (max_chunk_token_size as f32 * ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO)
             as u32,
-        overlap_percent,
-        tokenizer: Arc::new(tokenizer),
+        overlap: overlap_percent.map(OverlapStrategy::Percent),
+        text_mode: TextMode::Cleaned,
+        protected_spans: None,
+        separators: None,
+        tokenizer: TokenCounter::new(Arc::new(tokenizer)),
+        token_cache: Arc::default(),
         base_text: Arc::from(incoming_text),
+        offset_map: Arc::new(OffsetMap::identity(incoming_text.len())),
+        split_tables: Arc::default(),
         initial_separator: Separator::None,
         initial_splits: VecDeque::new(),
+        phase_durations: PhaseDurations::default(),
     });
     let mut chunks = Vec::new();
     for chunk in text_chunks.iter() {
@@ -182,7 +193,7 @@ mod tests {
 
         let chunks = res.chunks_to_text();
         for chunk in &chunks {
-            let token_count = res.tokenizer.count_tokens(chunk);
+            let token_count = res.sizers[0].size(chunk);
             chunk_token_sizes.push(token_count);
             all_chunks_token_count += token_count;
 
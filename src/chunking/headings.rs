@@ -0,0 +1,139 @@
+use super::*;
+use crate::splitting::markdown_headings;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// A numbered plain-text section title on its own line, such as `2 Installation` or `2.1. Linux`.
+static NUMBERED_HEADING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[ \t]*(?P<number>\d{1,2}(?:\.\d{1,2})*)\.?[ \t]+(?P<title>\p{Lu}[^\r\n]*?)[ \t]*\r?$")
+        .unwrap()
+});
+/// Numbered lines longer than this are treated as text rather than titles.
+const NUMBERED_HEADING_MAX_WORDS: usize = 12;
+/// Joins the titles of a heading path in a breadcrumb.
+const HEADING_PATH_SEPARATOR: &str = " > ";
+
+/// The heading paths of a text: the titles of each heading and the headings that enclose it, from the outermost in.
+/// Headings are detected in the incoming text rather than the cleaned text, because cleaning may remove the line breaks that mark them.
+#[derive(Default)]
+pub(super) struct HeadingPaths {
+    /// The starting byte offset of each heading in the incoming text, with its path. Sorted by offset.
+    headings: Vec<(usize, Vec<String>)>,
+}
+
+impl HeadingPaths {
+    /// Detects Markdown headings, including HTML headings converted by [`crate::clean_html::clean_html_with_heading_markers`], and, if `numbered` is true, numbered plain-text section titles.
+    pub(super) fn detect(text: &str, numbered: bool) -> Self {
+        let mut found: Vec<(usize, usize, &str)> = markdown_headings(text);
+        if numbered {
+            found.extend(numbered_headings(text));
+            found.sort_by_key(|(start, _, _)| *start);
+            found.dedup_by_key(|(start, _, _)| *start);
+        }
+        // The open heading at each level. A heading closes every heading of its own level or deeper.
+        let mut open: Vec<(usize, String)> = Vec::new();
+        let headings = found
+            .into_iter()
+            .map(|(start, level, title)| {
+                open.retain(|(open_level, _)| *open_level < level);
+                open.push((level, title.to_string()));
+                (start, open.iter().map(|(_, title)| title.clone()).collect())
+            })
+            .collect();
+        Self { headings }
+    }
+
+    /// The heading path in effect at `offset` in the incoming text. Empty if no heading precedes it.
+    pub(super) fn path_at(&self, offset: usize) -> &[String] {
+        let i = self.headings.partition_point(|(start, _)| *start <= offset);
+        match i {
+            0 => &[],
+            _ => &self.headings[i - 1].1,
+        }
+    }
+
+    /// The largest size of any breadcrumb prepended to a chunk, which is reserved from the maximum chunk size.
+    pub(super) fn max_breadcrumb_size(&self, sizer: &dyn ChunkSizer) -> u32 {
+        self.headings
+            .iter()
+            .map(|(_, path)| sizer.size(&breadcrumb(path)))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// The text prepended to a chunk for a heading path, such as `Installation > Linux > Troubleshooting` followed by a blank line.
+pub(super) fn breadcrumb(path: &[String]) -> String {
+    format!("{}\n\n", path.join(HEADING_PATH_SEPARATOR))
+}
+
+/// Finds numbered section titles: short capitalized lines after a blank line that start with a section number. The level is the number of parts in the section number.
+fn numbered_headings(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut headings = Vec::new();
+    let mut after_blank_line = true;
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        let line_start = start;
+        start += line.len();
+        if line.trim().is_empty() {
+            after_blank_line = true;
+            continue;
+        }
+        let is_first_line = std::mem::replace(&mut after_blank_line, false);
+        if !is_first_line {
+            continue;
+        }
+        let Some(captures) = NUMBERED_HEADING.captures(line.trim_end_matches('\n')) else {
+            continue;
+        };
+        let title = captures.name("title").unwrap().as_str();
+        if title.ends_with(['.', ',', ';', ':', '!', '?'])
+            || title.split_whitespace().count() > NUMBERED_HEADING_MAX_WORDS
+        {
+            continue;
+        }
+        let level = captures.name("number").unwrap().as_str().split('.').count();
+        headings.push((line_start, level, title));
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths() {
+        let text = "# Installation\n\nIntro.\n\n## Linux\n\nSteps.\n\n### Troubleshooting\n\nFixes.\n\n## Windows\n\nMore steps.\n";
+        let headings = HeadingPaths::detect(text, true);
+        assert_eq!(headings.path_at(0), ["Installation"]);
+        assert_eq!(
+            headings.path_at(text.find("Fixes").unwrap()),
+            ["Installation", "Linux", "Troubleshooting"]
+        );
+        assert_eq!(
+            headings.path_at(text.find("More steps").unwrap()),
+            ["Installation", "Windows"]
+        );
+        assert!(HeadingPaths::detect("No headings here.", true).path_at(5).is_empty());
+    }
+
+    #[test]
+    fn numbered() {
+        let text = "1 Introduction\n\nSome text.\n\n2. Installation\n\n2.1 Linux Systems\n\nRun the installer.\n\n3. Check that it worked.\n\n2024 was a good year\n";
+        let titles: Vec<(usize, &str)> = numbered_headings(text)
+            .into_iter()
+            .map(|(_, level, title)| (level, title))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![(1, "Introduction"), (1, "Installation"), (2, "Linux Systems")]
+        );
+        let headings = HeadingPaths::detect(text, true);
+        assert_eq!(
+            headings.path_at(text.find("Run").unwrap()),
+            ["Installation", "Linux Systems"]
+        );
+        assert!(HeadingPaths::detect(text, false).path_at(text.len()).is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// A chunk at one level of a [`HierarchicalResult`]. All ranges are byte ranges in the incoming text.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HierarchicalChunk {
+    /// The position of the chunk in [`HierarchicalResult::chunks`].
+    pub id: usize,
+    /// The level of the chunk. Level 0 holds the largest chunks.
+    pub level: usize,
+    pub text: String,
+    /// The source of the chunk, including any overlap.
+    pub source_range: Range<usize>,
+    /// The source of the chunk, excluding any overlap. Always within the core source range of the chunk's parent.
+    pub core_source_range: Range<usize>,
+    /// The id of the chunk one level up that contains this chunk. None at level 0.
+    pub parent: Option<usize>,
+    /// The ids of the chunks one level down that this chunk contains, in order.
+    pub children: Vec<usize>,
+    /// The id of the previous chunk with the same parent. At level 0, the previous chunk.
+    pub previous: Option<usize>,
+    /// The id of the next chunk with the same parent. At level 0, the next chunk.
+    pub next: Option<usize>,
+    /// The heading path of the chunk. See [`ChunkerResult::heading_paths`].
+    pub heading_path: Vec<String>,
+}
+
+/// The chunks of a text at several sizes, where the chunks of each level nest exactly within the chunks of the level above. Created by [`TextChunker::run_hierarchical`].
+/// For small-to-big retrieval, index the chunks of the last level and pass their ancestors to the LLM.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HierarchicalResult {
+    /// Every chunk, ordered by level, and within a level by position in the text.
+    pub chunks: Vec<HierarchicalChunk>,
+    /// The maximum chunk size of each level, from the largest.
+    pub level_sizes: Vec<u32>,
+}
+
+impl HierarchicalResult {
+    /// Chunks `incoming_text` with `chunker` for level 0, then chunks the core text of each chunk of a level with the next smaller size.
+    /// Every level splits on the same [`Separator`]s, so a child chunk's boundaries are boundaries its parent was built from or finer ones inside it.
+    pub(super) fn new(
+        chunker: &TextChunker,
+        incoming_text: &str,
+        child_chunk_token_sizes: &[u32],
+    ) -> Result<Self, ChunkerError> {
+        let mut level_sizes = vec![chunker.absolute_length_max];
+        for size in child_chunk_token_sizes {
+            if *size == 0 || *size >= *level_sizes.last().unwrap() {
+                return Err(ChunkerError::InvalidConfig(format!(
+                    "child chunk sizes must be greater than 0 and decrease from max_chunk_token_size: {level_sizes:?} followed by {size}",
+                )));
+            }
+            level_sizes.push(*size);
+        }
+        if level_sizes.len() < 2 {
+            return Err(ChunkerError::InvalidConfig(
+                "hierarchical chunking needs at least one child chunk size".to_string(),
+            ));
+        }
+
+        // Every level stops at the same deadline, so the time budget covers the whole hierarchy.
+        let deadline = chunker.deadline(std::time::Instant::now());
+        let mut result = chunker.text_chunker(incoming_text)?;
+        let texts = result.chunks_to_text();
+        let mut chunks: Vec<HierarchicalChunk> = result
+            .chunks
+            .iter()
+            .zip(texts)
+            .enumerate()
+            .map(|(id, (chunk, text))| HierarchicalChunk {
+                id,
+                level: 0,
+                text,
+                source_range: chunk.source_range(),
+                core_source_range: chunk.core_source_range(),
+                parent: None,
+                children: Vec::new(),
+                previous: None,
+                next: None,
+                heading_path: result.heading_paths.get(id).cloned().unwrap_or_default(),
+            })
+            .collect();
+        link_siblings(&mut chunks);
+
+        // Heading paths are looked up in the whole text, because a parent's text may start below the headings that enclose it.
+        let headings = match chunker.code_language {
+            Some(_) => HeadingPaths::default(),
+            None => HeadingPaths::detect(incoming_text, !chunker.markdown),
+        };
+        let mut parents = 0..chunks.len();
+        for (level, size) in level_sizes.iter().enumerate().skip(1) {
+            let child_chunker = chunker.child_level(*size, deadline);
+            let children: Vec<Vec<HierarchicalChunk>> = chunks[parents.clone()]
+                .par_iter()
+                .map(|parent| {
+                    let offset = parent.core_source_range.start;
+                    let parent_text = &incoming_text[parent.core_source_range.clone()];
+                    if parent_text.trim().is_empty() {
+                        return Ok(Vec::new());
+                    }
+                    let mut result = child_chunker.text_chunker(parent_text)?;
+                    let texts = result.chunks_to_text();
+                    let mut children: Vec<HierarchicalChunk> = result
+                        .chunks
+                        .iter()
+                        .zip(texts)
+                        .map(|(chunk, text)| {
+                            let source_range = chunk.source_range();
+                            let core_source_range = chunk.core_source_range();
+                            HierarchicalChunk {
+                                id: 0,
+                                level,
+                                text,
+                                source_range: source_range.start + offset
+                                    ..source_range.end + offset,
+                                heading_path: headings
+                                    .path_at(core_source_range.start + offset)
+                                    .to_vec(),
+                                core_source_range: core_source_range.start + offset
+                                    ..core_source_range.end + offset,
+                                parent: Some(parent.id),
+                                children: Vec::new(),
+                                previous: None,
+                                next: None,
+                            }
+                        })
+                        .collect();
+                    link_siblings(&mut children);
+                    Ok(children)
+                })
+                .collect::<Result<_, ChunkerError>>()?;
+
+            let level_start = chunks.len();
+            for siblings in children {
+                // Sibling links were set relative to the first sibling, and are offset to ids once the siblings are placed.
+                let first_id = chunks.len();
+                for mut child in siblings {
+                    child.id = chunks.len();
+                    child.previous = child.previous.map(|i| i + first_id);
+                    child.next = child.next.map(|i| i + first_id);
+                    chunks[child.parent.unwrap()].children.push(child.id);
+                    chunks.push(child);
+                }
+            }
+            parents = level_start..chunks.len();
+        }
+        Ok(Self {
+            chunks,
+            level_sizes,
+        })
+    }
+
+    /// The chunks of `level`, in order. Level 0 holds the largest chunks.
+    pub fn level(&self, level: usize) -> impl Iterator<Item = &HierarchicalChunk> {
+        self.chunks.iter().filter(move |chunk| chunk.level == level)
+    }
+
+    /// The chunks of the last level, the smallest chunks.
+    pub fn leaves(&self) -> impl Iterator<Item = &HierarchicalChunk> {
+        self.level(self.level_sizes.len() - 1)
+    }
+
+    /// The chunk one level up that contains `chunk`.
+    pub fn parent(&self, chunk: &HierarchicalChunk) -> Option<&HierarchicalChunk> {
+        chunk.parent.map(|id| &self.chunks[id])
+    }
+
+    /// The chunk at level 0 that contains `chunk`, or `chunk` itself at level 0.
+    pub fn root<'a>(&'a self, mut chunk: &'a HierarchicalChunk) -> &'a HierarchicalChunk {
+        while let Some(parent) = self.parent(chunk) {
+            chunk = parent;
+        }
+        chunk
+    }
+}
+
+/// Links each chunk to its neighbors, as positions in `siblings`.
+fn link_siblings(siblings: &mut [HierarchicalChunk]) {
+    let count = siblings.len();
+    for (i, chunk) in siblings.iter_mut().enumerate() {
+        chunk.previous = i.checked_sub(1);
+        chunk.next = (i + 1 < count).then_some(i + 1);
+    }
+}
+
+impl TextChunker {
+    /// A [`TextChunker`] with the same settings that chunks to `max_chunk_token_size`, for a child level of a [`HierarchicalResult`].
+    /// The minimum chunk size reverts to its default, and heading paths are not prepended, because a child's text is a part of its parent's text.
+    /// Chunking stops at `deadline`, which is shared by every level.
+    fn child_level(&self, max_chunk_token_size: u32, deadline: Option<std::time::Instant>) -> Self {
+        Self {
+            sizer: Arc::clone(&self.sizer),
+            additional_sizers: self.additional_sizers.clone(),
+            absolute_length_max: max_chunk_token_size,
+            absolute_length_min: None,
+            overlap: self.overlap,
+            use_dfs_semantic_splitter: self.use_dfs_semantic_splitter,
+            deterministic: self.deterministic,
+            markdown: self.markdown,
+            code_language: self.code_language,
+            embedder: self.embedder.clone(),
+            semantic_window: self.semantic_window,
+            semantic_breakpoint_percentile: self.semantic_breakpoint_percentile,
+            prepend_heading_path: false,
+            text_mode: self.text_mode,
+            cancellation_token: self.cancellation_token.clone(),
+            time_budget: self.time_budget,
+            deadline,
+            protected_spans: self.protected_spans.clone(),
+            separators: self.separators.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn nested_levels() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(256)
+            .overlap(OverlapStrategy::Tokens(16))
+            .run_hierarchical(content, &[96, 32])
+            .unwrap();
+        assert_eq!(res.level_sizes, vec![256, 96, 32]);
+        assert!(res.level(0).count() > 1);
+        assert!(res.leaves().count() > res.level(1).count());
+
+        for (id, chunk) in res.chunks.iter().enumerate() {
+            assert_eq!(chunk.id, id);
+            assert!(chunk.source_range.start <= chunk.core_source_range.start);
+            assert!(chunk.core_source_range.end <= chunk.source_range.end);
+            if let Some(parent) = res.parent(chunk) {
+                assert_eq!(parent.level + 1, chunk.level);
+                assert!(parent.children.contains(&id));
+                // Children nest exactly within their parent's core range, overlap included.
+                assert!(parent.core_source_range.start <= chunk.source_range.start);
+                assert!(chunk.source_range.end <= parent.core_source_range.end);
+            }
+            if let Some(next) = chunk.next {
+                let next = &res.chunks[next];
+                assert_eq!(next.previous, Some(id));
+                assert_eq!(next.parent, chunk.parent);
+                assert!(chunk.core_source_range.end <= next.core_source_range.start);
+            }
+            assert_eq!(res.root(chunk).level, 0);
+        }
+        for chunk in res.level(0) {
+            assert!(!chunk.children.is_empty());
+            let first = &res.chunks[chunk.children[0]];
+            let last = &res.chunks[*chunk.children.last().unwrap()];
+            assert_eq!(first.previous, None);
+            assert_eq!(last.next, None);
+        }
+
+        let chunker = TextChunker::new().unwrap().max_chunk_token_size(256);
+        assert!(matches!(
+            chunker.run_hierarchical(content, &[96, 128]),
+            Err(ChunkerError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            chunker.run_hierarchical(content, &[]),
+            Err(ChunkerError::InvalidConfig(_))
+        ));
+
+        // Child levels stop at the deadline of the whole run rather than starting their own time budget.
+        let child_chunker = chunker
+            .time_budget(std::time::Duration::from_secs(600))
+            .child_level(96, Some(std::time::Instant::now()));
+        assert!(matches!(
+            child_chunker.run(content),
+            Err(ChunkerError::TimedOut)
+        ));
+    }
+}
@@ -19,7 +19,7 @@ pub struct LinearChunker {
 
 impl LinearChunker {
     /// This is called from [`TextChunker`], but you can call it by creating a [`ChunkerConfig`] and passing it in.
-    pub fn run(config: &Arc<ChunkerConfig>) -> Option<Vec<Chunk>> {
+    pub fn run(config: &Arc<ChunkerConfig>) -> Result<Vec<Chunk>, ChunkerError> {
         let mut chunker = Self {
             unused_splits: config.initial_splits.clone(),
             config: Arc::clone(config),
@@ -32,9 +32,7 @@ impl LinearChunker {
         // let mut chunk_times = Vec::new();
         chunker.update_estimates();
         while chunker.chunk_count > 0 {
-            if chunker.config.chunks_found.load(Ordering::Relaxed) {
-                return None;
-            }
+            chunker.config.chunks_found.check()?;
             // let start = std::time::Instant::now();
             let mut chunk = Chunk::new(&chunker.config);
             // Ran once with estimated 'synthetic' token counts.
@@ -48,7 +46,7 @@ impl LinearChunker {
         // let avg_chunk_time =
         //     chunk_times.iter().sum::<std::time::Duration>() / chunk_times.len() as u32;
         // println!("Average chunk time: {:?}", avg_chunk_time);
-        Some(chunker.chunks)
+        Ok(chunker.chunks)
     }
 
     /// Ran for each [`Chunk`].
@@ -71,13 +69,6 @@ impl LinearChunker {
         let modifier = self.config.length_max * 0.0005 * self.chunk_count as f32;
         let actual_token_count = self.remaining_token_count - modifier;
         if self.chunk_count == 1 {
-            if self.remaining_token_count > self.config.length_max {
-                eprintln!(
-                    "Chunk count is 1 but remaining_token_count is greater than length_max: {:#?}",
-                    self.remaining_token_count
-                );
-            }
-
             self.length_min = self.remaining_token_count;
             self.length_max = self.config.length_max;
         } else {
@@ -101,7 +92,7 @@ impl LinearChunker {
     /// 'estimated' calls the function with estimated token counts. This is much more effecient than calling it with actual token counts, and is used first to get the chunk close enough to the min and max length.
     /// With 'estimated' false, the function is called with actual token counts. This ensures that the chunk is within the min and max length.
     /// By calling the function first with estimated token counts, we avoid calling the tokenizer as much as possible.
-    fn chunk_builder(&mut self, mut chunk: Chunk, estimated: bool) -> Option<Chunk> {
+    fn chunk_builder(&mut self, mut chunk: Chunk, estimated: bool) -> Result<Chunk, ChunkerError> {
         let length_max = if estimated {
             // Add a small buffer to the length_max when using the estimated token counts.
             let length_max = self.length_max + (self.length_max * 0.001).ceil();
@@ -129,24 +120,24 @@ impl LinearChunker {
                 chunk.add_split(split, false);
             }
             if estimated {
-                return Some(chunk);
+                return Ok(chunk);
             }
-            if chunk.token_count(estimated) >= length_min
-                && chunk.token_count(estimated) <= length_max
+            // The final chunk is checked once against the tokenizer.
+            if chunk.exact_token_count() >= length_min
+                && chunk.exact_token_count() <= length_max
             {
-                return Some(chunk);
+                return Ok(chunk);
             } else {
-                eprintln!(
-                    "\nseparator: {:?}\nChunk count is 1 but final chunk token count is not within min and max.",
-                    self.config.initial_separator
-                );
-                return None;
+                return Err(self.config.impossible(format!(
+                    "the final chunk's token count: {} is not within min: {} and max: {}",
+                    chunk.token_count(estimated),
+                    length_min,
+                    length_max
+                )));
             }
         }
         loop {
-            if self.config.chunks_found.load(Ordering::Relaxed) {
-                return None;
-            }
+            self.config.chunks_found.check()?;
             while chunk.token_count(estimated) < length_min && !self.unused_splits.is_empty() {
                 let split = self.unused_splits.pop_front().unwrap();
                 chunk.add_split(split, false);
@@ -160,14 +151,16 @@ impl LinearChunker {
             if chunk.token_count(estimated) >= length_min
                 && chunk.token_count(estimated) <= length_max
             {
-                return Some(chunk);
+                // Counted incrementally, so check the chunk once against the tokenizer. If it is over, keep building with full counts.
+                if estimated || chunk.verify_token_count(length_max) {
+                    return Ok(chunk);
+                }
+                continue;
             }
             if self.unused_splits.is_empty() {
-                eprintln!(
-                    "\nseparator: {:?}\nunused_splits is empty, but chunk token count is not within min and max.",
-                    self.config.initial_separator
-                );
-                return None;
+                return Err(self.config.impossible(
+                    "no splits remain, but the chunk's token count is not within min and max",
+                ));
             }
             // Split the removed split, and push the new splits back into the pool of unused_splits.
             let split = self.unused_splits.pop_front().unwrap();
@@ -193,8 +186,8 @@ mod tests {
         separator: Separator,
         incoming_text: &str,
         absolute_length_max: u32,
-    ) -> Option<Vec<Chunk>> {
-        let chunks_found: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    ) -> Result<Vec<Chunk>, ChunkerError> {
+        let chunks_found = ChunksFound::new(true);
 
         let config = Arc::new(ChunkerConfig::new(
             &chunks_found,
@@ -202,8 +195,11 @@ mod tests {
             incoming_text,
             absolute_length_max,
             None,
-            Some(0.0),
-            Arc::clone(tokenizer),
+            Some(OverlapStrategy::Percent(0.0)),
+            TextMode::Cleaned,
+            None,
+            None,
+            TokenCounter::new(tokenizer.clone()),
         )?);
 
         LinearChunker::run(&config)
@@ -1,24 +1,63 @@
+mod dedup;
 mod dfs_chunker;
+mod error;
+mod export;
 #[cfg(test)]
 mod external_text_chunker;
+mod headings;
+mod hierarchy;
 mod linear_chunker;
 mod overlap;
+mod packing;
+mod presets;
+mod report;
+mod semantic;
+mod sizer;
+mod stats;
+mod stream;
+mod token_cache;
 
-use crate::splitting::{Separator, SeparatorGroup, TextSplit, TextSplitter};
+use crate::{
+    clean_text::OffsetMap,
+    splitting::{
+        find_tables, CodeLanguage, ProtectedSpans, Separator, SeparatorGroup, TextSplit,
+        TextSplitter,
+    },
+};
 
+pub use dedup::{DedupReport, Deduplicator, Fingerprint, FingerprintMethod};
 use dfs_chunker::DfsTextChunker;
+pub use error::ChunkerError;
+pub use export::{ChunkRecord, JsonlWriter};
+use headings::{breadcrumb, HeadingPaths};
+pub use hierarchy::{HierarchicalChunk, HierarchicalResult};
 use linear_chunker::LinearChunker;
 use llm_models::tokenizer::LlmTokenizer;
 use overlap::OverlapChunker;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+pub use overlap::OverlapStrategy;
+pub use packing::{ContextPacker, PackedContext, PackingStrategy};
+pub use presets::ChunkingPurpose;
+pub use report::{AttemptOutcome, ChunkerKind, ChunkingReport, PhaseDurations, SeparatorAttempt};
+use report::TokenCounter;
+pub use semantic::Embedder;
+use semantic::{SEMANTIC_BREAKPOINT_PERCENTILE_DEFAULT, SEMANTIC_WINDOW_DEFAULT};
+use sizer::LimitedSizers;
+pub use sizer::{ByteSizer, CharSizer, ChunkSizer, FnSizer, WordSizer};
+pub use stats::{ChunkStats, SizeStats};
+pub use stream::ChunkStream;
+use stream::ReadPieces;
+use token_cache::TokenCache;
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 use std::{
     collections::VecDeque,
+    ops::Range,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
 };
-use anyhow::Result;
 
 /// An easy alternative to the [`TextChunker`] struct.  
 /// 
@@ -29,14 +68,32 @@ pub fn chunk_text(
     text: &str,
     max_chunk_token_size: u32,
     overlap_percent: Option<f32>,
-) -> Result<Option<Vec<String>>> {
+) -> Result<Vec<String>, ChunkerError> {
     let mut splitter = TextChunker::new()?.max_chunk_token_size(max_chunk_token_size);
     if let Some(overlap_percent) = overlap_percent {
         splitter = splitter.overlap_percent(overlap_percent);
     }
-    Ok(splitter.run(text))
+    splitter.run(text)
 }
 
+/// How the text of each chunk is built from the incoming text. Set with [`TextChunker::text_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextMode {
+    /// The text is cleaned before it is split, and each chunk's splits are joined with normalized whitespace.
+    #[default]
+    Cleaned,
+    /// The text is split as is, and each chunk is an exact slice of the incoming text that keeps the whitespace after it.
+    /// The core ranges of the chunks cover the incoming text, so concatenating the chunks without their overlap reconstructs it byte for byte.
+    Lossless,
+    /// Like [`TextMode::Lossless`], but chunks start and end on text rather than whitespace. The whitespace between chunks is in no chunk.
+    LosslessTrimmed,
+}
+
+impl TextMode {
+    fn is_lossless(&self) -> bool {
+        matches!(self, Self::Lossless | Self::LosslessTrimmed)
+    }
+}
 
 const ABSOLUTE_LENGTH_MAX_DEFAULT: u32 = 1024;
 const ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO: f32 = 0.75;
@@ -44,39 +101,102 @@ const TOKENIZER_TIKTOKEN_DEFAULT: &str = "gpt-4";
 
 /// Splits text by paragraphs, newlines, sentences, spaces, and finally graphemes, and builds chunks from the splits that are within the desired token ranges.
 pub struct TextChunker {
-    /// An atomic reference to the tokenizer. Defaults to the TikToken tokenizer.
-    tokenizer: Arc<LlmTokenizer>,
+    /// Measures chunk sizes. Defaults to the TikToken tokenizer.
+    sizer: Arc<dyn ChunkSizer>,
+    /// Additional sizers, each with its own inclusive limit, that chunks must also satisfy. Default is empty.
+    additional_sizers: Vec<(Arc<dyn ChunkSizer>, u32)>,
     /// Inclusive hard limit.
     absolute_length_max: u32,     
     /// This is used solely for the [`DfsTextChunker`] to determine the minimum chunk size. Default is 75% of the `absolute_length_max`.
     absolute_length_min: Option<u32>,     
-    /// The percentage of overlap between chunks. Default is None.
-    overlap_percent: Option<f32>, 
+    /// How chunks overlap their neighbors. Default is None.
+    overlap: Option<OverlapStrategy>,
     /// Whether to use the DFS semantic splitter to attempt to build valid chunks. Default is true.
     use_dfs_semantic_splitter: bool,
+    /// Whether to return the result of the highest-priority successful [`Separator`], so the output never depends on thread scheduling. Default is true.
+    deterministic: bool,
+    /// Whether to chunk on Markdown structure instead of plain text separators. Default is false.
+    markdown: bool,
+    /// The language to chunk source code by. Takes precedence over `markdown`. Default is None.
+    code_language: Option<CodeLanguage>,
+    /// Embeds sentences to place chunk boundaries where the topic changes. Takes precedence over `code_language` and `markdown`. Default is None.
+    embedder: Option<Arc<dyn Embedder>>,
+    /// The number of sentences on each side of a candidate boundary compared by semantic chunking. Default is 2.
+    semantic_window: usize,
+    /// The percentile of dissimilarity above which semantic chunking places a boundary. Default is 0.9.
+    semantic_breakpoint_percentile: f32,
+    /// Whether to prepend each chunk's heading path to its text. Default is false.
+    prepend_heading_path: bool,
+    /// How the text of each chunk is built from the incoming text. Default is [`TextMode::Cleaned`].
+    text_mode: TextMode,
+    /// Stops chunking when cancelled by the caller. Default is None.
+    cancellation_token: Option<CancellationToken>,
+    /// The longest chunking a text may take. Default is None.
+    time_budget: Option<std::time::Duration>,
+    /// A deadline shared by the levels of [`TextChunker::run_hierarchical`] that replaces `time_budget`. Default is None.
+    deadline: Option<std::time::Instant>,
+    /// Spans that chunk and split boundaries never fall inside. Default is None.
+    protected_spans: Option<Arc<ProtectedSpans>>,
+    /// A user-defined separator hierarchy that replaces the built-in separators. Default is None.
+    separators: Option<Arc<[Separator]>>,
 }
 
 impl TextChunker {
     /// Creates a new instance of the [`TextChunker`] struct using the default TikToken tokenizer.
-    pub fn new() -> Result<Self> {
+    pub fn new() -> Result<Self, ChunkerError> {
         Ok(Self {
-            tokenizer: Arc::new(LlmTokenizer::new_tiktoken(
-                TOKENIZER_TIKTOKEN_DEFAULT,
-            )?),
+            sizer: Arc::new(
+                LlmTokenizer::new_tiktoken(TOKENIZER_TIKTOKEN_DEFAULT)
+                    .map_err(|e| ChunkerError::Tokenizer(e.into()))?,
+            ),
+            additional_sizers: Vec::new(),
             absolute_length_max: ABSOLUTE_LENGTH_MAX_DEFAULT,
             absolute_length_min: None,
-            overlap_percent: None,
+            overlap: None,
             use_dfs_semantic_splitter: true,
+            deterministic: true,
+            markdown: false,
+            code_language: None,
+            embedder: None,
+            semantic_window: SEMANTIC_WINDOW_DEFAULT,
+            semantic_breakpoint_percentile: SEMANTIC_BREAKPOINT_PERCENTILE_DEFAULT,
+            prepend_heading_path: false,
+            text_mode: TextMode::Cleaned,
+            cancellation_token: None,
+            time_budget: None,
+            deadline: None,
+            protected_spans: None,
+            separators: None,
         })
     }
     /// Creates a new instance of the [`TextChunker`] struct using a custom tokenizer. For example a Hugging Face tokenizer.
     pub fn new_with_tokenizer(custom_tokenizer: &Arc<LlmTokenizer>) -> Self {
+        Self::new_with_sizer(custom_tokenizer.clone())
+    }
+
+    /// Creates a new instance of the [`TextChunker`] struct that sizes chunks with a [`ChunkSizer`], such as [`CharSizer`] or an [`FnSizer`] wrapping any tokenizer.
+    /// Chunk sizes set with `max_chunk_token_size` and `min_chunk_token_size` are then in the sizer's units.
+    pub fn new_with_sizer(sizer: Arc<dyn ChunkSizer>) -> Self {
         Self {
-            tokenizer: Arc::clone(custom_tokenizer),
+            sizer,
+            additional_sizers: Vec::new(),
             absolute_length_max: ABSOLUTE_LENGTH_MAX_DEFAULT,
             absolute_length_min: None,
-            overlap_percent: None,
+            overlap: None,
             use_dfs_semantic_splitter: true,
+            deterministic: true,
+            markdown: false,
+            code_language: None,
+            embedder: None,
+            semantic_window: SEMANTIC_WINDOW_DEFAULT,
+            semantic_breakpoint_percentile: SEMANTIC_BREAKPOINT_PERCENTILE_DEFAULT,
+            prepend_heading_path: false,
+            text_mode: TextMode::Cleaned,
+            cancellation_token: None,
+            time_budget: None,
+            deadline: None,
+            protected_spans: None,
+            separators: None,
         }
     }
 
@@ -88,6 +208,24 @@ impl TextChunker {
         self
     }
 
+    /// Adds another tokenizer with its own maximum token size, so one set of chunks fits several models.
+    /// Every chunk is within `max_chunk_token_size` of this tokenizer, as well as within the limits of the [`TextChunker`]'s own tokenizer and any others added.
+    ///
+    /// * `tokenizer` - The additional tokenizer.
+    /// * `max_chunk_token_size` - The maxium token size for this tokenizer. Inclusive.
+    pub fn add_tokenizer(self, tokenizer: &Arc<LlmTokenizer>, max_chunk_token_size: u32) -> Self {
+        self.add_sizer(tokenizer.clone(), max_chunk_token_size)
+    }
+
+    /// Adds another [`ChunkSizer`] with its own maximum size. See [`TextChunker::add_tokenizer`].
+    ///
+    /// * `sizer` - The additional sizer.
+    /// * `max_chunk_size` - The maximum size in the sizer's units. Inclusive.
+    pub fn add_sizer(mut self, sizer: Arc<dyn ChunkSizer>, max_chunk_size: u32) -> Self {
+        self.additional_sizers.push((sizer, max_chunk_size));
+        self
+    }
+
     /// Sets the minimum token size for the chunks. Default is 75% of the `absolute_length_max`. Used solely for the [`DfsTextChunker`] to determine the minimum chunk size.
     /// 
     /// * `min_chunk_token_size` - The minimum token sized to be chunked to..
@@ -105,260 +243,960 @@ impl TextChunker {
         self
     }
 
+    /// Every [`Separator`] is attempted in parallel. In deterministic mode the result of the highest-priority successful [`Separator`] is returned, so the same input always produces the same chunks.
+    /// Otherwise the first attempt to succeed is returned, which can be faster but depends on thread scheduling.
+    ///
+    /// * `deterministic` - Whether to return the highest-priority result. Default is true.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Stops chunking with [`ChunkerError::CancelledByCaller`] once `cancellation_token` is cancelled, such as when an ingestion worker shuts down.
+    /// The chunkers check the token as they search, so a run stops shortly after it is cancelled. Default is None.
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Stops chunking with [`ChunkerError::TimedOut`] once chunking a text has taken longer than `time_budget`, so a pathological text can not stall the caller.
+    /// The budget applies to each text, and to each document of a batch. Default is None.
+    pub fn time_budget(mut self, time_budget: std::time::Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// When chunking that started at `chunking_start_time` must stop: the shared deadline if one is set, or the end of the time budget.
+    pub(super) fn deadline(
+        &self,
+        chunking_start_time: std::time::Instant,
+    ) -> Option<std::time::Instant> {
+        self.deadline
+            .or_else(|| self.time_budget.map(|budget| chunking_start_time + budget))
+    }
+
+    /// Never places a chunk or split boundary inside `protected_spans`, such as URLs, inline code, or template markers. See [`ProtectedSpans`].
+    /// Each span stays whole within one chunk, so a span larger than `max_chunk_token_size` makes chunking fail. Default is None.
+    pub fn protected_spans(mut self, protected_spans: ProtectedSpans) -> Self {
+        self.protected_spans = Some(Arc::new(protected_spans));
+        self
+    }
+
+    /// Chunks on a user-defined hierarchy of separators instead of the built-in ones, such as [`Separator::Custom`] page breaks or articles. Takes precedence over `code_language` and `markdown`.
+    /// Every separator is attempted as the initial separator, in order of priority. A split too large for a chunk is split on the next separator in the hierarchy, and after the last on the built-in separators that follow it.
+    /// To extend rather than replace the built-in separators, append [`Separator::get_all`]. Each separator must appear once.
+    pub fn separators(mut self, separators: Vec<Separator>) -> Self {
+        self.separators = Some(separators.into());
+        self
+    }
+
+    /// Chunks the text on Markdown structure. Default is false.
+    /// Boundaries are preferred at headings in order of level (h1 before h2 before h3), then at list items and paragraphs.
+    /// Fenced code blocks and tables are kept intact unless they are larger than a chunk, and whitespace such as code indentation is preserved.
+    ///
+    /// * `markdown` - Whether to use the Markdown separators. Default is false.
+    pub fn markdown(mut self, markdown: bool) -> Self {
+        self.markdown = markdown;
+        self
+    }
+
+    /// Chunks source code on the block structure of `language`. Default is None, which chunks natural language text.
+    /// Boundaries are preferred between top-level items, then between items of successively deeper blocks, and finally between lines.
+    /// Comments, doc comments, attributes, and decorators stay with the item that follows them, and the text is not cleaned so whitespace is preserved exactly.
+    ///
+    /// * `language` - The programming language of the incoming text.
+    pub fn code(mut self, language: CodeLanguage) -> Self {
+        self.code_language = Some(language);
+        self
+    }
+
+    /// Chunks the text on changes of topic detected with an [`Embedder`]. Default is None.
+    /// The text is split into sentences, each sentence is embedded, and boundaries are placed where the similarity of adjacent windows of sentences drops.
+    /// Sections between boundaries that are larger than `max_chunk_token_size` are chunked with the balanced chunkers, but chunks never span a boundary.
+    ///
+    /// Combining it with [`TextChunker::code`] or [`TextChunker::separators`] is a [`ChunkerError::InvalidConfig`].
+    ///
+    /// * `embedder` - Embeds the sentences.
+    pub fn semantic(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Sets how many sentences on each side of a candidate boundary are averaged and compared in semantic chunking. Default is 2.
+    /// Larger windows ignore brief digressions.
+    pub fn semantic_window(mut self, semantic_window: usize) -> Self {
+        self.semantic_window = semantic_window.max(1);
+        self
+    }
+
+    /// Sets the percentile of dissimilarity between adjacent windows above which semantic chunking places a boundary. Default is 0.9.
+    /// Higher values place fewer boundaries.
+    ///
+    /// * `semantic_breakpoint_percentile` - Between 0.0 and 1.0.
+    pub fn semantic_breakpoint_percentile(mut self, semantic_breakpoint_percentile: f32) -> Self {
+        self.semantic_breakpoint_percentile = semantic_breakpoint_percentile.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Prepends each chunk's heading path, such as `Installation > Linux > Troubleshooting`, to its text followed by a blank line. Default is false.
+    /// The breadcrumb counts against `max_chunk_token_size`: the size of the longest breadcrumb in the text is reserved from every chunk.
+    /// Heading paths are always available from [`ChunkerResult::heading_paths`]. When streaming, only headings within the current window are known.
+    ///
+    /// * `prepend_heading_path` - Whether to prepend the heading path. Default is false.
+    pub fn prepend_heading_path(mut self, prepend_heading_path: bool) -> Self {
+        self.prepend_heading_path = prepend_heading_path;
+        self
+    }
+
+    /// Sets how the text of each chunk is built from the incoming text. See [`TextMode`].
+    /// In the lossless modes heading paths can not be prepended, and the header rows of split tables are not repeated, because neither is part of the incoming text.
+    ///
+    /// * `text_mode` - Default is [`TextMode::Cleaned`].
+    pub fn text_mode(mut self, text_mode: TextMode) -> Self {
+        self.text_mode = text_mode;
+        self
+    }
+
     /// Sets the percentage of overlap between chunks. Default is None.
     /// The full percentage is used foward for the first chunk, and backwards for the last chunk.
     /// Middle chunks evenly split the percentage between forward and backwards.
     /// 
-    /// * `overlap_percent` - The percentage of overlap between chunks. Minimum is 0.01, and maximum is 0.5. Default is None.
-    pub fn overlap_percent(mut self, overlap_percent: f32) -> Self {
-        self.overlap_percent = if !(0.01..=0.5).contains(&overlap_percent) {
-            Some(0.10)
-        } else {
-            Some(overlap_percent)
-        };
+    /// * `overlap_percent` - The percentage of overlap between chunks. At least 0.0 and less than 1.0, or running returns [`ChunkerError::InvalidConfig`]. Default is None.
+    pub fn overlap_percent(self, overlap_percent: f32) -> Self {
+        self.overlap(OverlapStrategy::Percent(overlap_percent))
+    }
+
+    /// Sets how chunks overlap their neighbors. Default is None. See [`OverlapStrategy`].
+    /// Overlapped chunks never exceed `max_chunk_token_size`, and invalid values are returned as [`ChunkerError::InvalidConfig`].
+    ///
+    /// * `overlap` - The overlap strategy.
+    pub fn overlap(mut self, overlap: OverlapStrategy) -> Self {
+        self.overlap = Some(overlap);
         self
     }
 
     /// Runs the [`TextChunker`] on the incoming text and returns the chunks as a vector of strings.
     /// 
     /// * `incoming_text` - The natural language text to chunk.
-    pub fn run(&self, incoming_text: &str) -> Option<Vec<String>> {
-        Some(self.text_chunker(incoming_text)?.chunks_to_text())
+    pub fn run(&self, incoming_text: &str) -> Result<Vec<String>, ChunkerError> {
+        Ok(self.text_chunker(incoming_text)?.chunks_to_text())
     }
 
     /// Runs the [`TextChunker`] on the incoming text and returns the chunks as a [`ChunkerResult`].
     /// The [`ChunkerResult`] contains the incoming text, the initial separator used, the chunks, the tokenizer, and the chunking duration. Useful for testing, benching, and diagnostics.
     /// 
     /// * `incoming_text` - The natural language text to chunk.
-    pub fn run_return_result(&self, incoming_text: &str) -> Option<ChunkerResult> {
+    pub fn run_return_result(&self, incoming_text: &str) -> Result<ChunkerResult, ChunkerError> {
         self.text_chunker(incoming_text)
     }
 
+    /// Runs the [`TextChunker`] on many documents at once and returns the chunks of each document as a vector of strings.
+    /// Documents are chunked in parallel with the same tokenizer, so it is loaded once rather than once per document.
+    ///
+    /// * `documents` - Pairs of a document id and the document's text.
+    ///
+    /// Returns each document id with its chunks or the error that document failed with, in the order the documents were given.
+    pub fn run_batch<K, T, I>(&self, documents: I) -> Vec<(K, Result<Vec<String>, ChunkerError>)>
+    where
+        I: IntoIterator<Item = (K, T)>,
+        K: Send,
+        T: AsRef<str> + Send,
+    {
+        self.run_batch_return_result(documents)
+            .into_iter()
+            .map(|(id, result)| (id, result.map(|mut result| result.chunks_to_text())))
+            .collect()
+    }
+
+    /// Runs the [`TextChunker`] on many documents at once and returns a [`ChunkerResult`] for each document. See [`TextChunker::run_batch`].
+    ///
+    /// * `documents` - Pairs of a document id and the document's text.
+    pub fn run_batch_return_result<K, T, I>(
+        &self,
+        documents: I,
+    ) -> Vec<(K, Result<ChunkerResult, ChunkerError>)>
+    where
+        I: IntoIterator<Item = (K, T)>,
+        K: Send,
+        T: AsRef<str> + Send,
+    {
+        // Documents and the separator attempts of each document share the rayon thread pool, so idle threads steal work across documents.
+        documents
+            .into_iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(id, text)| {
+                let result = self.text_chunker(text.as_ref());
+                (id, result)
+            })
+            .collect()
+    }
+
+    /// Chunks the incoming text at several sizes for small-to-big retrieval. Level 0 is chunked to `max_chunk_token_size`, and each level after is chunked from the chunks of the level above, so boundaries nest exactly.
+    /// Each chunk records its parent and its previous and next siblings. See [`HierarchicalResult`].
+    /// Each parent is chunked again to find its children, and a [`TextChunker::time_budget`] covers the whole run.
+    ///
+    /// * `incoming_text` - The natural language text to chunk.
+    /// * `child_chunk_token_sizes` - The maximum token size of each level below level 0, decreasing. At least one is required.
+    pub fn run_hierarchical(
+        &self,
+        incoming_text: &str,
+        child_chunk_token_sizes: &[u32],
+    ) -> Result<HierarchicalResult, ChunkerError> {
+        HierarchicalResult::new(self, incoming_text, child_chunk_token_sizes)
+    }
+
+    /// Chunks a stream of text incrementally with bounded memory, yielding each chunk as soon as it is final. See [`ChunkStream`].
+    ///
+    /// * `pieces` - The text to chunk, in order. Pieces may be split anywhere, even mid-word.
+    pub fn run_stream<'a, I>(&'a self, pieces: I) -> ChunkStream<'a>
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'a,
+    {
+        ChunkStream::new(self, pieces.into_iter().map(Ok))
+    }
+
+    /// Chunks UTF-8 text read from `reader` incrementally with bounded memory. See [`ChunkStream`].
+    ///
+    /// * `reader` - The source of the text, such as a file.
+    pub fn run_stream_reader<'a, R: std::io::Read + 'a>(&'a self, reader: R) -> ChunkStream<'a> {
+        ChunkStream::new(self, ReadPieces::new(reader))
+    }
+
+    /// The [`TextChunker`]'s own sizer followed by any added sizers.
+    fn sizers(&self) -> Vec<Arc<dyn ChunkSizer>> {
+        std::iter::once(Arc::clone(&self.sizer))
+            .chain(
+                self.additional_sizers
+                    .iter()
+                    .map(|(sizer, _)| Arc::clone(sizer)),
+            )
+            .collect()
+    }
+
     /// Backend runner for [`TextChunker`].
     /// Attempts to chunk the incoming text on all [`Separator`] first using the [`DfsTextChunker`] and then [`LinearChunker`].
-    /// Returns whichever [`Separator`] chunking attempt was successful first, and if none are successful, returns None.
+    /// Returns the successful attempt of the highest-priority [`Separator`]. In deterministic mode lower-priority attempts stop once a higher-priority attempt succeeds, otherwise all attempts stop once any attempt succeeds.
+    /// If none are successful, returns [`ChunkerError::AllSeparatorsFailed`] with the reason each attempt failed.
     /// If the incoming text is less than the `absolute_length_max`, it will return a single chunk.
-    fn text_chunker(&self, incoming_text: &str) -> Option<ChunkerResult> {
+    fn text_chunker(&self, incoming_text: &str) -> Result<ChunkerResult, ChunkerError> {
         let chunking_start_time = std::time::Instant::now();
-        // A flag to signal if chunks have been found, and for all other threads to stop searching.
-        let chunks_found: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-
-        // Parallize the search for the first successful chunking attempt.
-        Separator::get_all().par_iter().find_map_any(|separator| {
-            if chunks_found.load(Ordering::Relaxed) {
-                return None;
+        resolve_absolute_length_min(self.absolute_length_max, self.absolute_length_min)?;
+        if incoming_text.trim().is_empty() {
+            return Err(ChunkerError::TextTooShort(
+                "the incoming text is empty or only whitespace".to_string(),
+            ));
+        }
+        if self
+            .additional_sizers
+            .iter()
+            .any(|(_, max_chunk_size)| *max_chunk_size == 0)
+        {
+            return Err(ChunkerError::InvalidConfig(
+                "the max_chunk_token_size of each added tokenizer must be greater than 0".to_string(),
+            ));
+        }
+        // With several sizers, chunks are sized in units of the first sizer scaled to satisfy every sizer's limit.
+        let sizer: Arc<dyn ChunkSizer> = if self.additional_sizers.is_empty() {
+            Arc::clone(&self.sizer)
+        } else {
+            let mut limits = vec![(Arc::clone(&self.sizer), self.absolute_length_max)];
+            limits.extend(self.additional_sizers.iter().cloned());
+            Arc::new(LimitedSizers::new(limits))
+        };
+        // Markdown headings are detected in all text but source code. Numbered section titles are only detected in plain text.
+        let headings = match self.code_language {
+            Some(_) => HeadingPaths::default(),
+            None => HeadingPaths::detect(incoming_text, !self.markdown),
+        };
+        if let Some(separators) = &self.separators {
+            if separators.is_empty()
+                || separators.contains(&Separator::None)
+                || separators
+                    .iter()
+                    .enumerate()
+                    .any(|(i, separator)| separators[..i].contains(separator))
+            {
+                return Err(ChunkerError::InvalidConfig(
+                    "the separator hierarchy must be non-empty, and each separator must appear once".to_string(),
+                ));
             }
-            let config = Arc::new(ChunkerConfig::new(
-                &chunks_found,
-                separator.clone(),
-                incoming_text,
-                self.absolute_length_max,
-                self.absolute_length_min,
-                self.overlap_percent,
-                self.tokenizer(),
-            )?);
-            if chunks_found.load(Ordering::Relaxed) {
-                return None;
+        }
+        if self.embedder.is_some() && (self.code_language.is_some() || self.separators.is_some()) {
+            return Err(ChunkerError::InvalidConfig(
+                "semantic chunking splits on sentences, so it can not be combined with a code language or a separator hierarchy".to_string(),
+            ));
+        }
+        if self.prepend_heading_path && self.text_mode.is_lossless() {
+            return Err(ChunkerError::InvalidConfig(
+                "heading paths can not be prepended to chunks in a lossless text mode".to_string(),
+            ));
+        }
+        let absolute_length_max = if self.prepend_heading_path {
+            let breadcrumb_size = headings.max_breadcrumb_size(sizer.as_ref());
+            if breadcrumb_size >= self.absolute_length_max {
+                return Err(ChunkerError::InvalidConfig(format!(
+                    "the longest heading path is {breadcrumb_size} tokens, which leaves no room in max_chunk_token_size: {}",
+                    self.absolute_length_max
+                )));
             }
-            // If the text is less than the absolute_length_max, `initial_separator` will be set to Separator::None, and we return a single chunk.
-            if config.initial_separator == Separator::None {
-                chunks_found.store(true, Ordering::Relaxed);
-                return Some(ChunkerResult::new(incoming_text, &config, chunking_start_time, vec![Chunk::dummy_chunk(&config, incoming_text)]));
-                
-            };
-            // println!("Found config for separator: {:#?}", separator);
-            if config.initial_separator.group() == SeparatorGroup::Semantic && self.use_dfs_semantic_splitter {
-                let chunks: Option<Vec<Chunk>> = DfsTextChunker::run(&config);
-                if let Some(chunks) = chunks {
-                    let chunks = OverlapChunker::run(&config, chunks);
-                    match chunks {
-                        Ok(chunk) => {
-                        chunks_found.store(true, Ordering::Relaxed);
-                        println!(
-                            "\nSuccessfully Split with: DfsTextChunker on separator: {:#?}\ntotal chunking_duration: {:#?}.\n",
-                            separator,
-                            chunking_start_time.elapsed()
-                        );
-                        return Some(ChunkerResult::new(incoming_text, &config, chunking_start_time, chunk));
-                        },
-                        Err(e) => {
-                            eprintln!("Error: {:#?}", e);
-                        }
-                    }
+            let absolute_length_max = self.absolute_length_max - breadcrumb_size;
+            resolve_absolute_length_min(absolute_length_max, self.absolute_length_min)?;
+            absolute_length_max
+        } else {
+            self.absolute_length_max
+        };
+        if let Some(overlap) = &self.overlap {
+            overlap.validate(absolute_length_max)?;
+        }
+        if let Some(embedder) = &self.embedder {
+            let mut result = self.semantic_chunker(
+                incoming_text,
+                embedder,
+                sizer,
+                absolute_length_max,
+                chunking_start_time,
+            )?;
+            result.set_heading_paths(&headings, self.prepend_heading_path);
+            return Ok(result);
+        }
+        let split_tables =
+            Arc::new(self.find_split_tables(incoming_text, sizer.as_ref(), absolute_length_max)?);
+        // Signals when chunks have been found, so that attempts that can no longer be used stop searching.
+        let chunks_found = ChunksFound::new(self.deterministic).interruptible(
+            self.cancellation_token.clone(),
+            self.deadline(chunking_start_time),
+        );
+
+        let separators = if let Some(separators) = &self.separators {
+            separators.to_vec()
+        } else if let Some(language) = self.code_language {
+            Separator::get_all_code(language)
+        } else if self.markdown {
+            Separator::get_all_markdown()
+        } else {
+            Separator::get_all()
+        };
+
+        // Parallize the chunking attempts. Attempts that can no longer be used exit early with `ChunkerError::Cancelled`.
+        let attempts: Vec<(SeparatorAttempt, Result<ChunkerResult, ChunkerError>)> = separators
+            .par_iter()
+            .enumerate()
+            .map(|(priority, separator)| {
+                let attempt_start_time = std::time::Instant::now();
+                let mut attempt = SeparatorAttempt::new(separator);
+                let tokenizer = TokenCounter::new(Arc::clone(&sizer));
+                let result = self
+                    .chunk_on_separator(
+                        incoming_text,
+                        separator,
+                        absolute_length_max,
+                        &split_tables,
+                        chunks_found.with_priority(priority),
+                        tokenizer.clone(),
+                        &mut attempt.phase_durations,
+                    )
+                    .map(|(config, chunks, chunker)| {
+                        attempt.outcome = AttemptOutcome::Succeeded(chunker);
+                        ChunkerResult::new(incoming_text, &config, chunking_start_time, chunks)
+                    });
+                match &result {
+                    Ok(_) => (),
+                    Err(ChunkerError::Cancelled) => attempt.outcome = AttemptOutcome::Cancelled,
+                    Err(e) => attempt.outcome = AttemptOutcome::Failed(e.to_string()),
                 }
+                attempt.tokenizer_calls = tokenizer.calls();
+                attempt.boundary_tokenizer_calls = tokenizer.boundary_calls();
+                attempt.duration = attempt_start_time.elapsed();
+                (attempt, result)
+            })
+            .collect();
+
+        let mut report_attempts = Vec::with_capacity(attempts.len());
+        let mut selected = None;
+        let mut failures = Vec::new();
+        for (i, (attempt, result)) in attempts.into_iter().enumerate() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                separator = ?attempt.separator,
+                outcome = ?attempt.outcome,
+                tokenizer_calls = attempt.tokenizer_calls,
+                duration = ?attempt.duration,
+                "chunking attempt finished"
+            );
+            report_attempts.push(attempt);
+            match result {
+                Ok(result) if selected.is_none() => selected = Some((i, result)),
+                Ok(_) | Err(ChunkerError::Cancelled) => (),
+                Err(e) => failures.push(e),
             }
-            let chunks = LinearChunker::run(&config)?;
-            let chunks = OverlapChunker::run(&config, chunks);
-            match chunks {
+        }
+        let Some((selected, mut result)) = selected else {
+            // Attempts stopped by the caller failed for the caller's reason rather than their own.
+            if let Some(i) = failures.iter().position(|e| {
+                matches!(e, ChunkerError::CancelledByCaller | ChunkerError::TimedOut)
+            }) {
+                return Err(failures.swap_remove(i));
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                failures = failures.len(),
+                duration = ?chunking_start_time.elapsed(),
+                "chunking failed for every separator"
+            );
+            return Err(ChunkerError::AllSeparatorsFailed { failures });
+        };
+        let report = ChunkingReport {
+            attempts: report_attempts,
+            selected,
+            total_duration: chunking_start_time.elapsed(),
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            separator = ?report.separator(),
+            chunker = ?report.chunker(),
+            chunks = result.chunks.len(),
+            tokenizer_calls = report.tokenizer_calls(),
+            duration = ?report.total_duration,
+            "chunking succeeded"
+        );
+        result.report = Some(report);
+        result.sizers = self.sizers();
+        result.repeat_table_headers();
+        result.set_heading_paths(&headings, self.prepend_heading_path);
+        Ok(result)
+    }
+
+    /// Finds the tables too large for one chunk in the incoming text, because the cleaned text may have no line breaks between rows.
+    fn find_split_tables(
+        &self,
+        incoming_text: &str,
+        sizer: &dyn ChunkSizer,
+        absolute_length_max: u32,
+    ) -> Result<Vec<SplitTable>, ChunkerError> {
+        if self.code_language.is_some() || self.text_mode.is_lossless() {
+            return Ok(Vec::new());
+        }
+        let mut split_tables = Vec::new();
+        for table in find_tables(incoming_text) {
+            if sizer.size(&incoming_text[table.range.clone()]) <= absolute_length_max {
+                continue;
+            }
+            let header = format!("{}\n", &incoming_text[table.header.clone()]);
+            let header_size = sizer.size(&header);
+            if header_size >= absolute_length_max {
+                return Err(ChunkerError::InvalidConfig(format!(
+                    "the header of a table that must be split is {header_size} tokens, which leaves no room in max_chunk_token_size: {absolute_length_max}"
+                )));
+            }
+            resolve_absolute_length_min(
+                absolute_length_max - header_size,
+                self.absolute_length_min,
+            )?;
+            split_tables.push(SplitTable {
+                rows: table.header.end..table.range.end,
+                header,
+                header_size: header_size as f32,
+            });
+        }
+        Ok(split_tables)
+    }
+
+    /// A single chunking attempt with `separator` as the initial separator. Returns the chunks and the chunker that built them.
+    /// `absolute_length_max` is the maximum chunk size after any space reserved for heading paths.
+    fn chunk_on_separator(
+        &self,
+        incoming_text: &str,
+        separator: &Separator,
+        absolute_length_max: u32,
+        split_tables: &Arc<Vec<SplitTable>>,
+        chunks_found: ChunksFound,
+        tokenizer: TokenCounter,
+        phase_durations: &mut PhaseDurations,
+    ) -> Result<(Arc<ChunkerConfig>, Vec<Chunk>, ChunkerKind), ChunkerError> {
+        chunks_found.check()?;
+        let mut config = ChunkerConfig::new(
+            &chunks_found,
+            separator.clone(),
+            incoming_text,
+            absolute_length_max,
+            self.absolute_length_min,
+            self.overlap,
+            self.text_mode,
+            self.protected_spans.clone(),
+            self.separators.clone(),
+            tokenizer,
+        )?;
+        config.split_tables = Arc::clone(split_tables);
+        let config = Arc::new(config);
+        phase_durations.cleaning = config.phase_durations.cleaning;
+        phase_durations.splitting = config.phase_durations.splitting;
+        chunks_found.check()?;
+        // If the text is less than the absolute_length_max, `initial_separator` will be set to Separator::None, and we return a single chunk.
+        if config.initial_separator == Separator::None {
+            chunks_found.store();
+            let text = match self.text_mode {
+                TextMode::LosslessTrimmed => incoming_text.trim(),
+                _ => incoming_text,
+            };
+            let chunks = vec![Chunk::dummy_chunk(&config, text)];
+            return Ok((config, chunks, ChunkerKind::SingleChunk));
+        };
+        config.check_split_count()?;
+        let mut dfs_error = None;
+        if config.initial_separator.group() == SeparatorGroup::Semantic
+            && self.use_dfs_semantic_splitter
+        {
+            match Self::build_chunks(&config, ChunkerKind::Dfs, phase_durations) {
                 Ok(chunks) => {
-                    chunks_found.store(true, Ordering::Relaxed);
-                    println!(
-                        "\nSuccessfully Split with: LinearChunker on separator: {:#?}\ntotal chunking_duration: {:#?}.\n",
-                        separator,
-                        chunking_start_time.elapsed()
-                    );
-                    Some(ChunkerResult::new(incoming_text, &config, chunking_start_time, chunks))
-                }
-                Err(e) => {
-                    eprintln!("Error: {:#?}", e);
-                    None
+                    chunks_found.store();
+                    return Ok((config, chunks, ChunkerKind::Dfs));
                 }
+                Err(e) => dfs_error = Some(e),
             }
-        })
+        }
+        match Self::build_chunks(&config, ChunkerKind::Linear, phase_durations) {
+            Ok(chunks) => {
+                chunks_found.store();
+                Ok((config, chunks, ChunkerKind::Linear))
+            }
+            // Report both chunkers' reasons when the DFS chunker was attempted first.
+            Err(ChunkerError::ImpossibleForSeparator { separator, reason }) => {
+                let reason = match dfs_error {
+                    Some(ChunkerError::ImpossibleForSeparator {
+                        reason: dfs_reason, ..
+                    }) => format!("{dfs_reason}; {reason}"),
+                    _ => reason,
+                };
+                Err(ChunkerError::ImpossibleForSeparator { separator, reason })
+            }
+            Err(e) => Err(e),
+        }
     }
 
-    fn tokenizer(&self) -> Arc<LlmTokenizer> {
-        Arc::clone(&self.tokenizer)
+    /// Builds chunks with `chunker` and adds overlap, recording the time spent in each phase.
+    fn build_chunks(
+        config: &Arc<ChunkerConfig>,
+        chunker: ChunkerKind,
+        phase_durations: &mut PhaseDurations,
+    ) -> Result<Vec<Chunk>, ChunkerError> {
+        let phase_start_time = std::time::Instant::now();
+        let chunks = match chunker {
+            ChunkerKind::Dfs => DfsTextChunker::run(config),
+            _ => LinearChunker::run(config),
+        };
+        phase_durations.chunking += phase_start_time.elapsed();
+        let phase_start_time = std::time::Instant::now();
+        let chunks = OverlapChunker::run(config, chunks?);
+        phase_durations.overlap += phase_start_time.elapsed();
+        chunks
     }
+
+}
+
+/// Shared between the parallel chunking attempts on each [`Separator`], so that attempts stop once chunks that will be used instead are found.
+#[derive(Clone)]
+pub struct ChunksFound {
+    /// The priority of the best successful attempt so far. `usize::MAX` if no attempt has succeeded.
+    best_priority: Arc<AtomicUsize>,
+    /// The priority of this attempt. Lower is preferred.
+    priority: usize,
+    /// If true, an attempt only stops for a success of higher priority, so the result never depends on thread scheduling.
+    deterministic: bool,
+    /// Stops every attempt when cancelled by the caller.
+    cancellation_token: Option<CancellationToken>,
+    /// Stops every attempt once passed.
+    deadline: Option<std::time::Instant>,
+}
+
+impl ChunksFound {
+    fn new(deterministic: bool) -> Self {
+        Self {
+            best_priority: Arc::new(AtomicUsize::new(usize::MAX)),
+            priority: 0,
+            deterministic,
+            cancellation_token: None,
+            deadline: None,
+        }
+    }
+
+    /// Also stops every attempt when `cancellation_token` is cancelled or `deadline` passes.
+    fn interruptible(
+        mut self,
+        cancellation_token: Option<CancellationToken>,
+        deadline: Option<std::time::Instant>,
+    ) -> Self {
+        self.cancellation_token = cancellation_token;
+        self.deadline = deadline;
+        self
+    }
+
+    fn with_priority(&self, priority: usize) -> Self {
+        Self {
+            best_priority: Arc::clone(&self.best_priority),
+            priority,
+            deterministic: self.deterministic,
+            cancellation_token: self.cancellation_token.clone(),
+            deadline: self.deadline,
+        }
+    }
+
+    /// Records that this attempt succeeded.
+    fn store(&self) {
+        self.best_priority.fetch_min(self.priority, Ordering::Relaxed);
+    }
+
+    /// Returns [`ChunkerError::Cancelled`] if chunks were found that will be used instead of this attempt's.
+    /// Returns [`ChunkerError::CancelledByCaller`] or [`ChunkerError::TimedOut`] if the caller stopped chunking.
+    fn check(&self) -> Result<(), ChunkerError> {
+        self.interrupted()?;
+        if self.load() {
+            Err(ChunkerError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns [`ChunkerError::CancelledByCaller`] if the caller cancelled chunking, or [`ChunkerError::TimedOut`] if the deadline passed.
+    fn interrupted(&self) -> Result<(), ChunkerError> {
+        if self
+            .cancellation_token
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Err(ChunkerError::CancelledByCaller);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+        {
+            return Err(ChunkerError::TimedOut);
+        }
+        Ok(())
+    }
+
+    /// Whether chunks were found that will be used instead of this attempt's, or the caller stopped chunking, so it should stop.
+    fn load(&self) -> bool {
+        if self.interrupted().is_err() {
+            return true;
+        }
+        let best_priority = self.best_priority.load(Ordering::Relaxed);
+        if self.deterministic {
+            best_priority < self.priority
+        } else {
+            best_priority != usize::MAX
+        }
+    }
+}
+
+/// A handle for stopping a [`TextChunker`] from another thread. Clones share the same state. See [`TextChunker::cancellation_token`].
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops every run of a [`TextChunker`] holding this token, now and in the future.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Resolves the minimum chunk size, and checks that it is less than the maximum.
+fn resolve_absolute_length_min(
+    absolute_length_max: u32,
+    absolute_length_min: Option<u32>,
+) -> Result<u32, ChunkerError> {
+    let absolute_length_min = if let Some(absolute_length_min) = absolute_length_min {
+        absolute_length_min
+    } else {
+        (absolute_length_max as f32 * ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO) as u32
+    };
+    if absolute_length_max <= absolute_length_min {
+        return Err(ChunkerError::InvalidConfig(format!(
+            "max_chunk_token_size: {} must be greater than min_chunk_token_size: {}",
+            absolute_length_max, absolute_length_min
+        )));
+    }
+    Ok(absolute_length_min)
+}
+
+/// A table of the incoming text too large for one chunk.
+#[derive(Clone, Debug)]
+struct SplitTable {
+    /// The byte range of the rows after the header in the incoming text.
+    rows: Range<usize>,
+    /// The header rows followed by a line break.
+    header: String,
+    header_size: f32,
 }
 
 /// Configuration used by the [`TextChunker`], [`DfsTextChunker`], [`LinearChunker`], and [`OverlapChunker`] to build chunks.
 /// Instantiated by the [`TextChunker`] on each [`Separator`] and passed to the chunkers.
 pub struct ChunkerConfig {
-    chunks_found: Arc<AtomicBool>,
+    chunks_found: ChunksFound,
     absolute_length_max: u32,
     absolute_length_min: u32,
     length_max: f32,
-    overlap_percent: Option<f32>,
-    tokenizer: Arc<LlmTokenizer>,
+    overlap: Option<OverlapStrategy>,
+    text_mode: TextMode,
+    protected_spans: Option<Arc<ProtectedSpans>>,
+    /// The user-defined separator hierarchy, if any.
+    separators: Option<Arc<[Separator]>>,
+    tokenizer: TokenCounter,
+    /// Token counts of splits and junctions between splits, shared by every chunk built from this config.
+    token_cache: Arc<TokenCache>,
     base_text: Arc<str>,
+    /// Maps byte offsets in `base_text` back to byte offsets in the incoming text.
+    offset_map: Arc<OffsetMap>,
+    /// The tables too large for one chunk, whose chunks reserve room for the repeated header rows.
+    split_tables: Arc<Vec<SplitTable>>,
     initial_separator: Separator,
     initial_splits: VecDeque<TextSplit>,
+    /// Time spent cleaning and splitting the incoming text.
+    phase_durations: PhaseDurations,
 }
 
 impl ChunkerConfig {
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        chunks_found: &Arc<AtomicBool>,
+        chunks_found: &ChunksFound,
         separator: Separator,
         incoming_text: &str,
         absolute_length_max: u32,
         absolute_length_min: Option<u32>,
-        overlap_percent: Option<f32>,
-        tokenizer: Arc<LlmTokenizer>,
-    ) -> Option<Self> {
-        let length_max = if let Some(overlap_percent) = overlap_percent {
-            (absolute_length_max as f32 - (absolute_length_max as f32 * overlap_percent)).floor()
+        overlap: Option<OverlapStrategy>,
+        text_mode: TextMode,
+        protected_spans: Option<Arc<ProtectedSpans>>,
+        separators: Option<Arc<[Separator]>>,
+        tokenizer: TokenCounter,
+    ) -> Result<Self, ChunkerError> {
+        // Room is reserved in each chunk for the overlap added later.
+        let length_max = if let Some(overlap) = &overlap {
+            (absolute_length_max as f32 - overlap.reserved_length(absolute_length_max)).floor()
         } else {
             absolute_length_max as f32
         };
-        let absolute_length_min = if let Some(absolute_length_min) = absolute_length_min {
-            absolute_length_min
+        let absolute_length_min =
+            resolve_absolute_length_min(absolute_length_max, absolute_length_min)?;
+
+        let phase_start_time = std::time::Instant::now();
+        // Lossless chunks are slices of the incoming text, so it is split as is.
+        let (base_text, offset_map) = if text_mode.is_lossless() {
+            (incoming_text.to_string(), OffsetMap::identity(incoming_text.len()))
         } else {
-            (absolute_length_max as f32 * ABSOLUTE_LENGTH_MIN_DEFAULT_RATIO) as u32
+            separator.clean_text_with_offset_map(incoming_text)
         };
-        if absolute_length_max <= absolute_length_min {
-            panic!(
-                "\nA combination absolute_length_max: {:#?} and overlap_percent: {:#?} is less than or equal to absolute_length_min: {:#?}.",
-                absolute_length_max, overlap_percent, absolute_length_min
-            );
-        }
-
-
         let mut config = Self {
-            chunks_found: Arc::clone(chunks_found),
+            chunks_found: chunks_found.clone(),
             absolute_length_max,
             absolute_length_min,
             length_max,
-            overlap_percent,
+            overlap,
+            text_mode,
+            protected_spans,
+            separators,
             tokenizer,
-            base_text: Arc::from(separator.clean_text(incoming_text)),
+            token_cache: Arc::default(),
+            base_text: Arc::from(base_text),
+            offset_map: Arc::new(offset_map),
+            split_tables: Arc::default(),
             initial_separator: separator.clone(),
             initial_splits: VecDeque::new(),
+            phase_durations: PhaseDurations {
+                cleaning: phase_start_time.elapsed(),
+                ..Default::default()
+            },
         };
 
+        let phase_start_time = std::time::Instant::now();
         let cleaned_text_token_count = config.tokenizer.count_tokens(&config.base_text);
         if cleaned_text_token_count <= absolute_length_max {
             config.initial_separator = Separator::None;
-            return Some(config);
+            config.phase_durations.splitting = phase_start_time.elapsed();
+            return Ok(config);
         }
-        let splits = if let Some(mut splits) = TextSplitter::new()
+        config.initial_splits = config.split_base_text()?;
+        config.phase_durations.splitting = phase_start_time.elapsed();
+        Ok(config)
+    }
+
+    /// Splits the base text on the initial [`Separator`] and counts the tokens of each split.
+    fn split_base_text(&self) -> Result<VecDeque<TextSplit>, ChunkerError> {
+        let splits = if let Some(mut splits) = self
+            .splitter(&self.initial_separator)
             .recursive(false)
             .clean_text(false)
-            .on_separator(&separator)
-            .split_text(&config.base_text)
+            .split_text(&self.base_text)
         {
+            self.tile_splits(splits.make_contiguous(), &(0..self.base_text.len()));
             splits.iter_mut().for_each(|split| {
-                config.set_split_token_count(split);
+                self.set_split_token_count(split);
             });
             splits
         } else {
-            // eprintln!("No splits found for separator: {:#?}", separator);
-            return None;
+            return Err(self.impossible("no splits found"));
         };
-        let splits_token_count = config.estimate_splits_token_count(&splits);
-        let chunk_count = (splits_token_count / config.length_max).ceil() as usize;
-        if splits.len() < chunk_count {
-            eprintln!(
-                "\nChunking is impossible for separator: {:#?}. Splits count: {:#?} is less than the minimum chunk_count: {:#?}.",
-                separator,
-                splits.len(),
+        Ok(splits)
+    }
+
+    /// Fails early if there are fewer initial splits than the chunks needed to hold them.
+    fn check_split_count(&self) -> Result<(), ChunkerError> {
+        let splits_token_count = self.estimate_splits_token_count(&self.initial_splits);
+        let chunk_count = (splits_token_count / self.length_max).ceil() as usize;
+        if self.initial_splits.len() < chunk_count {
+            return Err(self.impossible(format!(
+                "splits count: {} is less than the minimum chunk_count: {}",
+                self.initial_splits.len(),
                 chunk_count,
-            );
-            return None;
+            )));
         };
+        Ok(())
+    }
+
+    /// A copy of this config that builds chunks from `splits` instead of the initial splits.
+    fn with_initial_splits(&self, splits: VecDeque<TextSplit>) -> Self {
+        Self {
+            chunks_found: self.chunks_found.clone(),
+            absolute_length_max: self.absolute_length_max,
+            absolute_length_min: self.absolute_length_min,
+            length_max: self.length_max,
+            overlap: self.overlap,
+            text_mode: self.text_mode,
+            protected_spans: self.protected_spans.clone(),
+            separators: self.separators.clone(),
+            tokenizer: self.tokenizer.clone(),
+            token_cache: Arc::clone(&self.token_cache),
+            base_text: Arc::clone(&self.base_text),
+            offset_map: Arc::clone(&self.offset_map),
+            split_tables: Arc::clone(&self.split_tables),
+            initial_separator: self.initial_separator.clone(),
+            initial_splits: splits,
+            phase_durations: PhaseDurations::default(),
+        }
+    }
 
-        config.initial_splits = splits;
-        Some(config)
+    /// A [`TextSplitter`] on `separator` that keeps any protected spans whole and follows any user-defined separator hierarchy.
+    fn splitter(&self, separator: &Separator) -> TextSplitter {
+        let mut splitter = TextSplitter::new();
+        if let Some(separators) = &self.separators {
+            splitter = splitter.separators(Arc::clone(separators));
+        }
+        let splitter = splitter.on_separator(separator);
+        match &self.protected_spans {
+            Some(protected_spans) => splitter.protected_spans(Arc::clone(protected_spans)),
+            None => splitter,
+        }
+    }
+
+    /// The split table whose rows contain `base_offset` of the base text, if any.
+    fn split_table_at(&self, base_offset: usize) -> Option<&SplitTable> {
+        if self.split_tables.is_empty() {
+            return None;
+        }
+        let source = self
+            .offset_map
+            .source_range(&(base_offset..base_offset))
+            .start;
+        self.split_tables
+            .iter()
+            .find(|table| table.rows.start < source && source < table.rows.end)
+    }
+
+    /// A [`ChunkerError::ImpossibleForSeparator`] for this config's initial [`Separator`].
+    fn impossible(&self, reason: impl Into<String>) -> ChunkerError {
+        ChunkerError::ImpossibleForSeparator {
+            separator: self.initial_separator.clone(),
+            reason: reason.into(),
+        }
     }
 
     /// Splits an existing [`TextSplit`] into multiple [`TextSplit`]s on the next [`Separator`].
     /// If no splits are found, at attempts split on the following [`Separator`].
-    /// If it reaches the final [`Separator`] without successfully splitting, it returns None.
-    fn split_split(&self, split: TextSplit) -> Option<VecDeque<TextSplit>> {
-        let mut new_splits: VecDeque<TextSplit> = match split.split() {
+    /// If it reaches the final [`Separator`] without successfully splitting, it returns an error.
+    fn split_split(&self, split: TextSplit) -> Result<VecDeque<TextSplit>, ChunkerError> {
+        let next_separator = match &self.separators {
+            Some(separators) => split.split_separator.next_in(separators),
+            None => split.split_separator.next(),
+        };
+        let new_splits = next_separator.and_then(|separator| {
+            self.splitter(&separator)
+                .split_split(&split.base_text, &split.indices)
+        });
+        let mut new_splits: VecDeque<TextSplit> = match new_splits {
             Some(splits) => splits,
             None => {
-                // eprintln!(
-                //     "No splits found for split: {:#?}",
-                //     split.base_text.chars().take(50).collect::<String>()
-                // );
-                return None;
+                return Err(self.impossible(format!(
+                    "a split could not be split further: {:?}",
+                    split.text().chars().take(50).collect::<String>()
+                )));
             }
         };
+        self.tile_splits(new_splits.make_contiguous(), &split.indices);
         new_splits.iter_mut().for_each(|split| {
             self.set_split_token_count(split);
         });
-        Some(new_splits)
+        Ok(new_splits)
+    }
+
+    /// In [`TextMode::Lossless`], extends splits over the whitespace between them, so together they cover `range` of the base text exactly.
+    /// The whitespace after a split is kept with it, and any leading whitespace with the first split.
+    fn tile_splits(&self, splits: &mut [TextSplit], range: &Range<usize>) {
+        if self.text_mode != TextMode::Lossless || splits.is_empty() {
+            return;
+        }
+        let ends: Vec<usize> = splits
+            .iter()
+            .skip(1)
+            .map(|split| split.indices.start)
+            .chain(std::iter::once(range.end))
+            .collect();
+        for (split, end) in splits.iter_mut().zip(ends) {
+            split.indices.end = end;
+        }
+        splits[0].indices.start = range.start;
     }
 
     fn set_split_token_count(&self, split: &mut TextSplit) {
         if split.token_count.is_none() {
-            let token_count = self.tokenizer.count_tokens(split.text());
+            let token_count = self.token_cache.split_token_count(&self.tokenizer, split);
             split.token_count = Some(token_count);
         }
     }
 
+    /// How many tokens joining `left` and the following `right` adds to the sum of their token counts.
+    fn junction_token_count(&self, left: &TextSplit, right: &TextSplit) -> i64 {
+        let joiner = if self.text_mode.is_lossless() {
+            self.base_text
+                .get(left.indices.end..right.indices.start)
+                .unwrap_or_default()
+        } else {
+            TextSplitter::joiner(left, right, false)
+        };
+        self.token_cache
+            .junction_token_count(&self.tokenizer, left, right, joiner)
+    }
+
     /// Estimates the token count of the splits.
     /// This is used for estimating the remaining token count, and is also used to estimate the token count of chunks.
     /// It is somewhat accurate.
     fn estimate_splits_token_count(&self, splits: &VecDeque<TextSplit>) -> f32 {
-        let mut last_separator = Separator::None;
+        let mut last_separator = None;
         let mut total_tokens = 0.0;
         for split in splits {
-            let split_tokens = match split.split_separator {
-                Separator::GraphemesUnicode => match last_separator {
-                    Separator::None | Separator::GraphemesUnicode => 0.55,
-                    _ => 1.0,
-                },
-                _ => split.token_count.unwrap() as f32,
-            };
-            if last_separator != Separator::None {
-                let white_space_ratio = match split.split_separator {
-                    Separator::None => {
-                        unreachable!()
-                    }
-                    Separator::TwoPlusEoL => 0.999,
-                    Separator::SingleEol => 0.999,
-                    Separator::SentencesRuleBased => 0.998,
-                    Separator::SentencesUnicode => 0.998,
-                    Separator::WordsUnicode => 0.89,
-                    Separator::GraphemesUnicode => 1.0,
-                };
-                total_tokens += split_tokens * white_space_ratio;
-            } else {
-                total_tokens += split_tokens;
-            }
-            last_separator = split.split_separator.clone();
+            total_tokens += self.tokenizer.estimate_split_size(
+                split.token_count.unwrap(),
+                &split.split_separator,
+                last_separator,
+            );
+            last_separator = Some(&split.split_separator);
         }
         total_tokens
     }
@@ -369,8 +1207,15 @@ impl ChunkerConfig {
 pub struct Chunk {
     text: Option<String>,
     used_splits: VecDeque<TextSplit>,
+    /// The token count of the chunk's text as measured by the tokenizer.
     token_count: Option<usize>,
     estimated_token_count: f32,
+    /// The token count kept up to date from the split and junction token counts as splits are added and removed. None if it can not be derived.
+    incremental_token_count: Option<i64>,
+    /// Whether the incremental token count is trusted. Cleared if it ever disagrees with the tokenizer about a chunk's validity.
+    count_incrementally: bool,
+    /// The range of the chunk in the base text before the [`OverlapChunker`] added overlap.
+    core_range: Option<Range<usize>>,
     config: Arc<ChunkerConfig>,
 }
 
@@ -381,6 +1226,9 @@ impl Chunk {
             used_splits: VecDeque::new(),
             token_count: Some(0),
             estimated_token_count: 0.0,
+            incremental_token_count: Some(0),
+            count_incrementally: true,
+            core_range: None,
             config: Arc::clone(config),
         }
     }
@@ -391,11 +1239,31 @@ impl Chunk {
             used_splits: VecDeque::new(),
             token_count: Some(0),
             estimated_token_count: 0.0,
+            incremental_token_count: None,
+            count_incrementally: false,
+            core_range: None,
             config: Arc::clone(config),
         }
     }
 
     fn add_split(&mut self, split: TextSplit, backwards: bool) {
+        let neighbor = if backwards {
+            self.used_splits.front()
+        } else {
+            self.used_splits.back()
+        };
+        self.incremental_token_count = match (self.base_token_count(), neighbor, split.token_count) {
+            (Some(_), None, Some(split_token_count)) => Some(split_token_count as i64),
+            (Some(token_count), Some(neighbor), Some(split_token_count)) => {
+                let junction = if backwards {
+                    self.config.junction_token_count(&split, neighbor)
+                } else {
+                    self.config.junction_token_count(neighbor, &split)
+                };
+                Some(token_count + split_token_count as i64 + junction)
+            }
+            _ => None,
+        };
         if backwards {
             self.used_splits.push_front(split);
         } else {
@@ -407,28 +1275,94 @@ impl Chunk {
     }
     
     fn remove_split(&mut self, backwards: bool) -> TextSplit {
+        let base_token_count = self.base_token_count();
         let split = if backwards {
             self.used_splits.pop_front().unwrap()
         } else {
             self.used_splits.pop_back().unwrap()
         };
+        let neighbor = if backwards {
+            self.used_splits.front()
+        } else {
+            self.used_splits.back()
+        };
+        self.incremental_token_count = match (base_token_count, neighbor, split.token_count) {
+            (Some(_), None, _) => Some(0),
+            (Some(token_count), Some(neighbor), Some(split_token_count)) => {
+                let junction = if backwards {
+                    self.config.junction_token_count(&split, neighbor)
+                } else {
+                    self.config.junction_token_count(neighbor, &split)
+                };
+                Some(token_count - split_token_count as i64 - junction)
+            }
+            _ => None,
+        };
         self.estimated_token_count = self.config.estimate_splits_token_count(&self.used_splits);
         self.token_count = None;
         self.text = None;
         split
     }
 
+    /// The best known token count of the chunk, to update incrementally when a split is added or removed. None if the chunk is counted in full.
+    fn base_token_count(&self) -> Option<i64> {
+        if !self.count_incrementally {
+            return None;
+        }
+        match self.token_count {
+            Some(token_count) => Some(token_count as i64),
+            None => self.incremental_token_count,
+        }
+    }
+
+    /// The token count of the chunk. If `estimated`, the sum of the split estimates.
+    /// Otherwise the incremental count, which only calls the tokenizer on the short windows around new junctions, or if it is unavailable, the count of the whole text.
     fn token_count(&mut self, estimated: bool) -> f32 {
-        if let Some(token_count) = self.token_count {
+        let token_count = if let Some(token_count) = self.token_count {
             token_count as f32
         } else if estimated {
             self.estimated_token_count
         } else {
-            let text = &self.text();
-            let token_count = self.config.tokenizer.count_tokens(text) as usize;
-            self.token_count = Some(token_count);
-            self.estimated_token_count = token_count as f32;
-            token_count as f32
+            match self.base_token_count() {
+                Some(token_count) => token_count.max(0) as f32,
+                None => self.measured_token_count(),
+            }
+        };
+        token_count + self.reserved_token_count()
+    }
+
+    /// The token count of the chunk's whole text, as measured by the tokenizer, with any room reserved for a table header.
+    fn exact_token_count(&mut self) -> f32 {
+        self.measured_token_count() + self.reserved_token_count()
+    }
+
+    /// The room reserved for the header rows repeated in a chunk that starts within the rows of a split table.
+    fn reserved_token_count(&self) -> f32 {
+        self.config
+            .split_table_at(self.core_base_text_range().start)
+            .map_or(0.0, |table| table.header_size)
+    }
+
+    /// The token count of the chunk's whole text, as measured by the tokenizer.
+    fn measured_token_count(&mut self) -> f32 {
+        if let Some(token_count) = self.token_count {
+            return token_count as f32;
+        }
+        let text = &self.text();
+        let token_count = self.config.tokenizer.count_tokens(text) as usize;
+        self.token_count = Some(token_count);
+        self.estimated_token_count = token_count as f32;
+        token_count as f32
+    }
+
+    /// Checks a chunk accepted by its incremental token count against the tokenizer. Called once per finished chunk.
+    /// If the chunk is actually over `length_max`, returns false and counts the chunk's whole text from then on.
+    fn verify_token_count(&mut self, length_max: f32) -> bool {
+        if self.exact_token_count() <= length_max {
+            true
+        } else {
+            self.count_incrementally = false;
+            false
         }
     }
 
@@ -436,22 +1370,68 @@ impl Chunk {
         if let Some(text) = &self.text {
             text.to_owned()
         } else {
-            let text = TextSplitter::splits_to_text(&self.used_splits, false);
+            let text = self.joined_text();
             self.text = Some(text.clone());
             text
         }
     }
 
+    /// The chunk's text, without caching it, for when the chunk is only borrowed.
+    /// In the lossless modes the text is the slice of the base text the splits span, otherwise the splits joined with normalized whitespace.
+    fn joined_text(&self) -> String {
+        match &self.text {
+            Some(text) => text.to_owned(),
+            None if self.config.text_mode.is_lossless() && !self.used_splits.is_empty() => {
+                let text = &self.config.base_text[self.base_text_range()];
+                match self.config.text_mode {
+                    TextMode::LosslessTrimmed => text.trim().to_string(),
+                    _ => text.to_string(),
+                }
+            }
+            None => TextSplitter::splits_to_text(&self.used_splits, false),
+        }
+    }
+
+    /// The byte range of the chunk within the cleaned text the chunker split. Includes any overlap.
+    pub fn base_text_range(&self) -> Range<usize> {
+        match (self.used_splits.front(), self.used_splits.back()) {
+            (Some(first), Some(last)) => first.indices.start..last.indices.end,
+            _ => 0..self.config.base_text.len(),
+        }
+    }
 
+    /// The byte range of the chunk within the original incoming text. Includes any overlap.
+    /// Use this to highlight or cite the source passage of a chunk.
+    pub fn source_range(&self) -> Range<usize> {
+        self.config.offset_map.source_range(&self.base_text_range())
+    }
+
+    /// The byte range of the chunk within the cleaned text, excluding any overlap. The core ranges of all chunks never overlap.
+    pub fn core_base_text_range(&self) -> Range<usize> {
+        self.core_range
+            .clone()
+            .unwrap_or_else(|| self.base_text_range())
+    }
 
+    /// The byte range of the chunk within the original incoming text, excluding any overlap.
+    pub fn core_source_range(&self) -> Range<usize> {
+        self.config
+            .offset_map
+            .source_range(&self.core_base_text_range())
+    }
 }
 
 pub struct ChunkerResult {
     incoming_text: Arc<str>,
+    base_text: Arc<str>,
     initial_separator: Separator,
     chunks: Vec<Chunk>,
-    tokenizer: Arc<LlmTokenizer>,
+    /// The sizers chunks were measured with. The [`TextChunker`]'s own sizer is first, followed by any added sizers.
+    sizers: Vec<Arc<dyn ChunkSizer>>,
     chunking_duration: std::time::Duration,
+    report: Option<ChunkingReport>,
+    /// The heading path of each chunk.
+    heading_paths: Vec<Vec<String>>,
 }
 
 impl ChunkerResult {
@@ -464,82 +1444,129 @@ impl ChunkerResult {
         chunks.iter_mut().for_each(|chunk| { chunk.text(); });
         ChunkerResult {
             incoming_text: Arc::from(incoming_text),
+            base_text: Arc::clone(&config.base_text),
             initial_separator: config.initial_separator.clone(),
-           chunks,
-            tokenizer: Arc::clone(&config.tokenizer),
+            chunks,
+            sizers: vec![config.tokenizer.sizer()],
             chunking_duration: chunking_start_time.elapsed(),
+            report: None,
+            heading_paths: Vec::new(),
+        }
+    }
+
+    /// Prepends the header rows of a split table to each chunk that starts within its rows.
+    fn repeat_table_headers(&mut self) {
+        for chunk in self.chunks.iter_mut() {
+            let Some(header) = chunk
+                .config
+                .split_table_at(chunk.core_base_text_range().start)
+                .map(|table| table.header.clone())
+            else {
+                continue;
+            };
+            let text = format!("{header}{}", chunk.text());
+            chunk.text = Some(text);
+            chunk.token_count = None;
+        }
+    }
+
+    /// Looks up the heading path of each chunk at the start of its core source range, and optionally prepends it to the chunk's text.
+    fn set_heading_paths(&mut self, headings: &HeadingPaths, prepend: bool) {
+        self.heading_paths = self
+            .chunks
+            .iter()
+            .map(|chunk| headings.path_at(chunk.core_source_range().start).to_vec())
+            .collect();
+        if !prepend {
+            return;
+        }
+        for (chunk, path) in self.chunks.iter_mut().zip(&self.heading_paths) {
+            if !path.is_empty() {
+                let text = format!("{}{}", breadcrumb(path), chunk.text());
+                chunk.text = Some(text);
+                chunk.token_count = None;
+            }
         }
     }
 
     pub fn chunks_to_text(&mut self) -> Vec<String> {
         self.chunks.iter_mut().map(|chunk| chunk.text()).collect()
     }
+
+    /// The incoming text as it was passed to the chunker.
+    pub fn incoming_text(&self) -> &str {
+        &self.incoming_text
+    }
+
+    /// The cleaned text the chunks were built from.
+    pub fn base_text(&self) -> &str {
+        &self.base_text
+    }
+
+    /// The heading path of each chunk: the titles of the heading the chunk falls under and of the headings enclosing it, from the outermost in.
+    /// Headings are Markdown headings, HTML headings converted by [`crate::clean_html::clean_html_with_heading_markers`], and in plain text, numbered section titles such as `2.1 Linux`.
+    /// A chunk before the first heading has an empty path.
+    pub fn heading_paths(&self) -> &[Vec<String>] {
+        &self.heading_paths
+    }
+
+    /// Describes the separators attempted, the chunker that succeeded, tokenizer usage, and per-phase timings.
+    pub fn report(&self) -> Option<&ChunkingReport> {
+        self.report.as_ref()
+    }
+
+    /// The byte range of each chunk within [`ChunkerResult::base_text`].
+    pub fn base_text_ranges(&self) -> Vec<Range<usize>> {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.base_text_range())
+            .collect()
+    }
+
+    /// The byte range of each chunk within [`ChunkerResult::incoming_text`].
+    pub fn source_ranges(&self) -> Vec<Range<usize>> {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.source_range())
+            .collect()
+    }
     
     pub fn token_counts(&mut self) -> Vec<u32> {
         let mut token_counts: Vec<u32> = Vec::with_capacity(self.chunks.len());
         for chunk in &self.chunks {
-            let chunk_text = if let Some(text) = &chunk.text {
-                text.to_owned()
-            } else {
-                TextSplitter::splits_to_text(&chunk.used_splits, false)
-            };
-            token_counts.push(self.tokenizer.count_tokens(&chunk_text));
+            token_counts.push(self.sizers[0].size(&chunk.joined_text()));
         }
         token_counts
     }
+
+    /// The token count of each chunk for every sizer, in the order the sizers were added to the [`TextChunker`] with its own sizer first.
+    pub fn token_counts_per_sizer(&mut self) -> Vec<Vec<u32>> {
+        let chunks = self.chunks_to_text();
+        self.sizers
+            .iter()
+            .map(|sizer| chunks.iter().map(|chunk| sizer.size(chunk)).collect())
+            .collect()
+    }
 }
 
 impl std::fmt::Debug for ChunkerResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut chunk_token_sizes = Vec::with_capacity(self.chunks.len());
-        let mut largest_token_size  = 0;
-        let mut smallest_token_size = u32::MAX;
-        let mut all_chunks_token_count = 0;
-        let mut chunk_char_sizes = Vec::with_capacity(self.chunks.len());
-        let mut largest_char_size = 0;
-        let mut smallest_char_size = u32::MAX;
-        let mut all_chunks_char_count = 0;
-
-     
-        for chunk in &self.chunks {
-            let chunk_text = if let Some(text) = &chunk.text {
-                text.to_owned()
-            } else {
-                panic!("Chunk text not found.")
-            };
-            let token_count = self.tokenizer.count_tokens(&chunk_text);
-            let char_count = u32::try_from(chunk_text.chars().count()).unwrap();
-            chunk_token_sizes.push(token_count);
-            chunk_char_sizes.push(char_count);
-            all_chunks_token_count += token_count;
-            all_chunks_char_count += char_count;
-            if token_count > largest_token_size {
-                largest_token_size = token_count;
-            }
-            if char_count > largest_char_size {
-                largest_char_size = char_count;
-            }
-            if token_count < smallest_token_size {
-                smallest_token_size = token_count;
-            }
-            if char_count < smallest_char_size {
-                smallest_char_size = char_count;
-            }
-        }
+        let stats = self.stats();
         f.debug_struct("\nChunkerTestResult")
-            .field("chunk_count", &self.chunks.len())
-            .field("chunk_token_sizes", &chunk_token_sizes)
-            .field("avg_token_size", &(all_chunks_token_count / u32::try_from(self.chunks.len()).unwrap()))
-            .field("largest_token_size", &largest_token_size)
-            .field("smallest_token_size", &smallest_token_size)
-            .field("incoming_text_token_count", &self.tokenizer.count_tokens(&self.incoming_text))
-            .field("all_chunks_token_count", &all_chunks_token_count)
-            .field("chunk_char_sizes", &chunk_char_sizes)
-            .field("avg_char_size", &(all_chunks_char_count / u32::try_from(self.chunks.len()).unwrap()))
-            .field("largest_char_size", &largest_char_size)
-            .field("smallest_char_size", &smallest_char_size)
-            .field("incoming_text_char_count", &self.incoming_text.chars().count())
-            .field("all_chunks_char_count", &all_chunks_char_count)
+            .field("chunk_count", &stats.chunk_count)
+            .field("chunk_token_sizes", &stats.chunk_token_sizes)
+            .field("avg_token_size", &stats.token_sizes.mean)
+            .field("largest_token_size", &stats.token_sizes.max)
+            .field("smallest_token_size", &stats.token_sizes.min)
+            .field("incoming_text_token_count", &stats.incoming_text_token_count)
+            .field("all_chunks_token_count", &stats.token_sizes.total)
+            .field("chunk_char_sizes", &stats.chunk_char_sizes)
+            .field("avg_char_size", &stats.char_sizes.mean)
+            .field("largest_char_size", &stats.char_sizes.max)
+            .field("smallest_char_size", &stats.char_sizes.min)
+            .field("incoming_text_char_count", &stats.incoming_text_char_count)
+            .field("all_chunks_char_count", &stats.char_sizes.total)
+            .field("balance_score", &stats.balance_score)
             .field("chunking_duration", &self.chunking_duration)
             .field("initial_separator", &self.initial_separator)
             .finish()
@@ -550,9 +1577,11 @@ impl std::fmt::Debug for ChunkerResult {
 mod tests {
     use super::*;
     use crate::test_text::*;
+    use anyhow::Result;
+    use crate::splitting::{CustomSeparator, MatchPlacement};
     use llm_models::local_model::gguf::preset::LlmPreset;
 
-    fn run_test(case: u32, content: &str, tokenizer: &Arc<LlmTokenizer>) -> Option<ChunkerResult> {
+    fn run_test(case: u32, content: &str, tokenizer: &Arc<LlmTokenizer>) -> Result<ChunkerResult, ChunkerError> {
        TextChunker::new_with_tokenizer(tokenizer)
         .max_chunk_token_size(case)
         .run_return_result(content)
@@ -670,6 +1699,151 @@ mod tests {
         }
     }
 
+    #[test]
+    fn source_ranges() {
+        let content = CHUNK_TESTS
+            .chunking_small
+            .content
+            .replace("\n\n", "\r\n\r\n\t");
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .run_return_result(&content)
+            .unwrap();
+        let source_ranges = res.source_ranges();
+        let base_text_ranges = res.base_text_ranges();
+        let chunks = res.chunks_to_text();
+        assert!(chunks.len() > 1);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let first_word = chunk.split_whitespace().next().unwrap();
+            let last_word = chunk.split_whitespace().last().unwrap();
+            assert!(res.base_text()[base_text_ranges[i].clone()].starts_with(first_word));
+            let source = &res.incoming_text()[source_ranges[i].clone()];
+            assert!(source.starts_with(first_word));
+            assert!(source.ends_with(last_word));
+        }
+    }
+
+    #[test]
+    fn markdown() {
+        let section = "Some text about the section that goes on for a little while.\n\n```rust\nfn main() {\n\n    println!(\"hello\");\n}\n```\n\n- first item\n- second item\n";
+        let content: String = (1..=6)
+            .map(|i| format!("# Heading {i}\n\n{section}\n"))
+            .collect();
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .markdown(true)
+            .run_return_result(&content)
+            .unwrap();
+        assert!(res.token_counts().iter().all(|&x| x <= 128));
+        let chunks = res.chunks_to_text();
+        assert!(chunks.len() > 1);
+        for chunk in chunks {
+            // Chunks are split on the highest-priority separator, the top level headings.
+            assert!(chunk.starts_with("# Heading"));
+            // Code fences are never split, and keep their indentation.
+            assert_eq!(chunk.matches("```").count() % 2, 0);
+            assert_eq!(
+                chunk.matches("```").count() / 2,
+                chunk.matches("\n    println!").count()
+            );
+        }
+    }
+
+    #[test]
+    fn heading_paths() {
+        let section = "Some text about the section that goes on for a little while, and then a little while longer.\n\n- first item\n- second item\n";
+        let content: String = std::iter::once("# Guide\n\n".to_string())
+            .chain((1..=4).map(|i| format!("## Part {i}\n\n{section}\n### Details {i}\n\n{section}\n")))
+            .collect();
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(64)
+            .markdown(true)
+            .run_return_result(&content)
+            .unwrap();
+        let paths = res.heading_paths().to_vec();
+        let chunks = res.chunks_to_text();
+        assert_eq!(paths.len(), chunks.len());
+        for (chunk, path) in chunks.iter().zip(&paths) {
+            assert_eq!(path[0], "Guide");
+            if chunk.starts_with('#') {
+                assert!(chunk.lines().next().unwrap().ends_with(path.last().unwrap().as_str()));
+            }
+        }
+        assert!(paths.contains(&vec!["Guide".to_string(), "Part 3".to_string(), "Details 3".to_string()]));
+
+        // Prepended breadcrumbs count against the maximum.
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(64)
+            .markdown(true)
+            .prepend_heading_path(true)
+            .run_return_result(&content)
+            .unwrap();
+        assert!(res.token_counts().iter().all(|&x| x <= 64));
+        assert!(res.chunks_to_text().iter().all(|chunk| chunk.starts_with("Guide")));
+    }
+
+    #[test]
+    fn code() {
+        let content: String = (1..=8)
+            .map(|i| {
+                format!(
+                    "/// Adds {i} to the value.\n#[inline]\npub fn add_{i}(\n    value: u32,\n) -> u32 {{\n    let result = value + {i};\n\n    result\n}}\n\n"
+                )
+            })
+            .collect();
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(96)
+            .code(CodeLanguage::Rust)
+            .run_return_result(&content)
+            .unwrap();
+        assert!(res.token_counts().iter().all(|&x| x <= 96));
+        let chunks = res.chunks_to_text();
+        assert!(chunks.len() > 1);
+        for chunk in chunks {
+            // Items are kept whole with their doc comments and attributes.
+            assert!(chunk.starts_with("/// Adds"));
+            assert!(chunk.ends_with("    result\n}"));
+            // Whitespace is preserved exactly.
+            assert!(content.contains(&chunk));
+        }
+    }
+
+    #[test]
+    fn deterministic() {
+        let chunker = TextChunker::new().unwrap().max_chunk_token_size(128);
+        let first = chunker.run(&CHUNK_TESTS.chunking_small.content).unwrap();
+        for _ in 0..5 {
+            let chunks = chunker.run(&CHUNK_TESTS.chunking_small.content).unwrap();
+            assert_eq!(chunks, first);
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let chunker = TextChunker::new().unwrap().max_chunk_token_size(128);
+        let documents = vec![
+            ("small", CHUNK_TESTS.chunking_small.content.as_str()),
+            ("empty", " "),
+            ("tiny", CHUNK_TESTS.chunking_tiny.content.as_str()),
+        ];
+        let results = chunker.run_batch(documents.clone());
+        assert_eq!(
+            results.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            ["small", "empty", "tiny"]
+        );
+        for ((_, text), (_, result)) in documents.iter().zip(results) {
+            match chunker.run(text) {
+                Ok(chunks) => assert_eq!(result.unwrap(), chunks),
+                Err(_) => assert!(matches!(result, Err(ChunkerError::TextTooShort(_)))),
+            }
+        }
+    }
+
     #[test]
     fn within_abs_max() {
         let  res = TextChunker::new().unwrap()
@@ -678,4 +1852,272 @@ mod tests {
             .unwrap();
         assert_eq!(res.chunks.len(), 1);
     }
+
+    #[test]
+    fn sizers() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let mut res = TextChunker::new_with_sizer(Arc::new(CharSizer))
+            .max_chunk_token_size(500)
+            .run_return_result(content)
+            .unwrap();
+        assert!(res.chunks.len() > 1);
+        assert!(res
+            .chunks_to_text()
+            .iter()
+            .all(|chunk| chunk.chars().count() <= 500));
+
+        let mut res = TextChunker::new_with_sizer(Arc::new(FnSizer::new(|text: &str| {
+            text.split_whitespace().count() as u32
+        })))
+        .max_chunk_token_size(100)
+        .run_return_result(content)
+        .unwrap();
+        assert!(res.token_counts().iter().all(|&x| x <= 100));
+    }
+
+    #[test]
+    fn multiple_sizers() {
+        let mut res = TextChunker::new_with_tokenizer(&tiktoken())
+            .max_chunk_token_size(256)
+            .add_tokenizer(&hf(), 200)
+            .add_sizer(Arc::new(CharSizer), 900)
+            .run_return_result(&CHUNK_TESTS.chunking_small.content)
+            .unwrap();
+        assert!(res.chunks.len() > 1);
+        let token_counts = res.token_counts_per_sizer();
+        assert_eq!(token_counts.len(), 3);
+        assert_eq!(token_counts[0], res.token_counts());
+        assert!(token_counts[0].iter().all(|&x| x <= 256));
+        assert!(token_counts[1].iter().all(|&x| x <= 200));
+        assert!(token_counts[2].iter().all(|&x| x <= 900));
+    }
+
+    #[test]
+    fn report() {
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .run_return_result(&CHUNK_TESTS.chunking_small.content)
+            .unwrap();
+        let report = res.report().unwrap();
+        assert_eq!(report.attempts.len(), Separator::get_all().len());
+        assert_eq!(report.separator(), &res.initial_separator);
+        assert!(matches!(
+            report.chunker(),
+            Some(ChunkerKind::Dfs) | Some(ChunkerKind::Linear)
+        ));
+        // Higher-priority attempts failed, and lower-priority attempts were never used.
+        for attempt in &report.attempts[..report.selected] {
+            assert!(matches!(attempt.outcome, AttemptOutcome::Failed(_)));
+        }
+        assert!(report.tokenizer_calls() >= report.selected_attempt().tokenizer_calls);
+        assert!(report.selected_attempt().tokenizer_calls > 0);
+        assert!(report.phase_durations().chunking <= report.total_duration);
+    }
+
+    #[test]
+    fn tables() {
+        let header = "| Item | Count |\n| --- | --- |";
+        let rows: String = (1..=40)
+            .map(|i| format!("| item number {i} | {} |\n", i * 7))
+            .collect();
+        let content = format!(
+            "Inventory as of this morning, counted by hand.\n\n{header}\n{rows}\nThe counts are checked again every evening.\n"
+        );
+        for markdown in [false, true] {
+            let chunks = TextChunker::new()
+                .unwrap()
+                .max_chunk_token_size(128)
+                .markdown(markdown)
+                .run(&content)
+                .unwrap();
+            assert!(chunks.len() > 2);
+            for chunk in &chunks {
+                if chunk.contains("| item number") {
+                    // Every chunk with rows of the split table repeats its header, and keeps one row per line.
+                    assert!(chunk.contains(header), "{chunk}");
+                    assert!(chunk.contains(" |\n| item number"), "{chunk}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tables_split_on_sentences() {
+        let header = "| Name | Value |\n| --- | --- |";
+        let rows: String = (1..=30).map(|i| format!("| row {i} | {i} |\n")).collect();
+        let content = format!(
+            "The values below were read from the meter.\n\n{header}\n{rows}\nEach value is read twice."
+        );
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(64)
+            .separators(vec![Separator::SentencesRuleBased])
+            .run_return_result(&content)
+            .unwrap();
+        assert_eq!(
+            res.report().unwrap().separator(),
+            &Separator::SentencesRuleBased
+        );
+        assert!(res.token_counts().iter().all(|count| *count <= 64));
+        let chunks = res.chunks_to_text();
+        let first = chunks
+            .iter()
+            .position(|chunk| chunk.contains(header))
+            .unwrap();
+        for chunk in &chunks[first + 1..] {
+            if chunk.contains("| row ") {
+                // The cleaned text has no line breaks between rows, so the table is found in the incoming text.
+                assert!(chunk.starts_with(header), "{chunk}");
+            }
+        }
+    }
+
+    #[test]
+    fn lossless() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        for overlap in [None, Some(OverlapStrategy::Tokens(16))] {
+            let mut chunker = TextChunker::new()
+                .unwrap()
+                .max_chunk_token_size(128)
+                .text_mode(TextMode::Lossless);
+            if let Some(overlap) = overlap {
+                chunker = chunker.overlap(overlap);
+            }
+            let mut res = chunker.run_return_result(content).unwrap();
+            assert!(res.token_counts().iter().all(|count| *count <= 128));
+            let texts = res.chunks_to_text();
+            let mut reconstructed = String::new();
+            for (chunk, text) in res.chunks.iter().zip(&texts) {
+                assert_eq!(text, &content[chunk.source_range()]);
+                reconstructed.push_str(&content[chunk.core_source_range()]);
+            }
+            assert_eq!(&reconstructed, content);
+        }
+
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .text_mode(TextMode::LosslessTrimmed)
+            .run_return_result(content)
+            .unwrap();
+        let texts = res.chunks_to_text();
+        for (chunk, text) in res.chunks.iter().zip(&texts) {
+            assert_eq!(text, content[chunk.source_range()].trim());
+        }
+
+        let res = TextChunker::new()
+            .unwrap()
+            .text_mode(TextMode::Lossless)
+            .prepend_heading_path(true)
+            .run(content);
+        assert!(matches!(res, Err(ChunkerError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn cancellation() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let token = CancellationToken::new();
+        let chunker = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .cancellation_token(token.clone());
+        assert!(chunker.run(content).is_ok());
+        token.cancel();
+        assert!(matches!(
+            chunker.run(content),
+            Err(ChunkerError::CancelledByCaller)
+        ));
+
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .time_budget(std::time::Duration::ZERO)
+            .run(content);
+        assert!(matches!(res, Err(ChunkerError::TimedOut)));
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .time_budget(std::time::Duration::from_secs(600))
+            .run(content);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn protected_spans() {
+        // A single run-on sentence, so chunks are built from words.
+        let content: String = (0..40)
+            .map(|i| {
+                format!("item {i} lives at https://example.com/items/{i}/details?ref=chunker and uses {{{{placeholder_{i}}}}} ")
+            })
+            .collect();
+        let spans = ProtectedSpans::builtins()
+            .pattern(r"\{\{[^}]+\}\}")
+            .unwrap();
+        for overlap in [None, Some(OverlapStrategy::Tokens(8))] {
+            let mut chunker = TextChunker::new()
+                .unwrap()
+                .max_chunk_token_size(48)
+                .protected_spans(spans.clone());
+            if let Some(overlap) = overlap {
+                chunker = chunker.overlap(overlap);
+            }
+            let chunks = chunker.run(&content).unwrap();
+            assert!(chunks.len() > 4);
+            for chunk in &chunks {
+                assert_eq!(chunk.matches("https://").count(), chunk.matches("?ref=chunker").count(), "{chunk}");
+                assert_eq!(chunk.matches("{{").count(), chunk.matches("}}").count(), "{chunk}");
+            }
+            for i in 0..40 {
+                let url = format!("https://example.com/items/{i}/details?ref=chunker");
+                assert!(chunks.iter().any(|chunk| chunk.contains(&url)));
+            }
+        }
+    }
+
+    #[test]
+    fn custom_separators() {
+        let content: String = (1..=12)
+            .map(|i| format!("Article {i} The parties agree that clause {i} applies to every delivery made under this agreement; the supplier shall notify the buyer of any delay within five days.\n"))
+            .collect();
+        let articles = Separator::Custom(CustomSeparator::new("article", r"(?m)^Article \d+").unwrap());
+        let clauses = Separator::Custom(
+            CustomSeparator::new("clause", ";")
+                .unwrap()
+                .placement(MatchPlacement::After),
+        );
+        let mut separators = vec![articles.clone(), clauses];
+        separators.extend(Separator::get_all());
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .separators(separators)
+            .run_return_result(&content)
+            .unwrap();
+        assert_eq!(res.report().unwrap().separator(), &articles);
+        let chunks = res.chunks_to_text();
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.starts_with("Article "), "{chunk}");
+        }
+
+        let res = TextChunker::new()
+            .unwrap()
+            .separators(vec![articles.clone(), articles])
+            .run(&content);
+        assert!(matches!(res, Err(ChunkerError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn errors() {
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .min_chunk_token_size(128)
+            .run(&CHUNK_TESTS.chunking_small.content);
+        assert!(matches!(res, Err(ChunkerError::InvalidConfig(_))));
+
+        let res = TextChunker::new().unwrap().run(" \n\n ");
+        assert!(matches!(res, Err(ChunkerError::TextTooShort(_))));
+    }
 }
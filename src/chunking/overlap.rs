@@ -1,5 +1,66 @@
 use super::*;
-use anyhow::anyhow;
+
+/// The share of `max_chunk_token_size` reserved for overlap by [`OverlapStrategy::Sentences`] and [`OverlapStrategy::Paragraphs`].
+const OVERLAP_UNITS_RESERVED_RATIO: f32 = 0.2;
+
+/// How much of its neighbors' text is added to each chunk as overlap. Set with [`TextChunker::overlap`].
+/// The first chunk only overlaps forward, the last chunk only overlaps backwards, and middle chunks overlap both ways.
+/// Overlapped chunks never exceed `max_chunk_token_size`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverlapStrategy {
+    /// Overlap by a share of each chunk's token count, between 0.0 and 1.0. Middle chunks evenly split it between forward and backwards.
+    /// The same share of `max_chunk_token_size` is reserved for overlap.
+    Percent(f32),
+    /// Overlap by about this many tokens. Middle chunks evenly split it between forward and backwards.
+    /// This many tokens of `max_chunk_token_size` are reserved for overlap.
+    Tokens(u32),
+    /// Overlap by up to this many whole sentences from each neighboring chunk, nearest first, so overlap never starts or ends mid-sentence.
+    /// 20% of `max_chunk_token_size` is reserved for overlap, and sentences that do not fit are left out.
+    Sentences(usize),
+    /// Overlap by up to this many whole paragraphs from each neighboring chunk, nearest first.
+    /// 20% of `max_chunk_token_size` is reserved for overlap, and paragraphs that do not fit are left out.
+    /// Paragraphs are only found when chunking on paragraphs or Markdown, because other separators clean away the line breaks between paragraphs.
+    Paragraphs(usize),
+}
+
+impl OverlapStrategy {
+    /// The length reserved from `absolute_length_max` for overlap.
+    pub(super) fn reserved_length(&self, absolute_length_max: u32) -> f32 {
+        match self {
+            Self::Percent(overlap_percent) => absolute_length_max as f32 * overlap_percent,
+            Self::Tokens(overlap_tokens) => *overlap_tokens as f32,
+            Self::Sentences(_) | Self::Paragraphs(_) => {
+                absolute_length_max as f32 * OVERLAP_UNITS_RESERVED_RATIO
+            }
+        }
+    }
+
+    /// Checks that the overlap leaves room for the chunks themselves.
+    pub(super) fn validate(&self, absolute_length_max: u32) -> Result<(), ChunkerError> {
+        if let Self::Percent(overlap_percent) = self {
+            if !(0.0..1.0).contains(overlap_percent) {
+                return Err(ChunkerError::InvalidConfig(format!(
+                    "overlap percent: {overlap_percent} must be at least 0.0 and less than 1.0"
+                )));
+            }
+        }
+        if (absolute_length_max as f32 - self.reserved_length(absolute_length_max)).floor() <= 0.0 {
+            return Err(ChunkerError::InvalidConfig(format!(
+                "overlap: {self:?} leaves no room in max_chunk_token_size: {absolute_length_max}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// The number of tokens of overlap to add to a chunk with `token_count` tokens, for the strategies that overlap by length.
+    fn overlap_tokens(&self, token_count: f32) -> f32 {
+        match self {
+            Self::Percent(overlap_percent) => token_count * overlap_percent,
+            Self::Tokens(overlap_tokens) => *overlap_tokens as f32,
+            Self::Sentences(_) | Self::Paragraphs(_) => 0.0,
+        }
+    }
+}
 
 /// Adds overlap to chunks built by the [`TextChunker`].
 pub struct OverlapChunker {
@@ -7,55 +68,125 @@ pub struct OverlapChunker {
     chunks: Vec<Chunk>,
     // A copy of the original chunks to be used as a source of unprocessed splits.
     chunks_copy: Vec<Chunk>,
-    overlap_percent: f32,
+    overlap: OverlapStrategy,
 }
 
 impl OverlapChunker {
-    pub fn run(config: &Arc<ChunkerConfig>, chunks: Vec<Chunk>) -> Result<Vec<Chunk>> {
+    pub fn run(
+        config: &Arc<ChunkerConfig>,
+        mut chunks: Vec<Chunk>,
+    ) -> Result<Vec<Chunk>, ChunkerError> {
         if chunks.is_empty() {
-            panic!("Chunks is empty. This should never happen.");
+            return Err(config.impossible("no chunks were built to add overlap to"));
         }
-        // If overlap_percent is None, 0.0, or the overlap would be less than 1 token, return the chunks as is.
-        let overlap_percent = if let Some(overlap_percent) = config.overlap_percent {
-            overlap_percent
+        // If overlap is None, 0, or would leave no room for the chunks, return the chunks as is.
+        let overlap = if let Some(overlap) = config.overlap {
+            overlap
         } else {
             return Ok(chunks);
         };
-        if overlap_percent == 0.0
-            || (config.absolute_length_max as f32
-                - (config.absolute_length_max as f32 * overlap_percent))
-                .floor()
-                <= 0.0
+        if matches!(
+            overlap,
+            OverlapStrategy::Percent(0.0)
+                | OverlapStrategy::Tokens(0)
+                | OverlapStrategy::Sentences(0)
+                | OverlapStrategy::Paragraphs(0)
+        ) || overlap.validate(config.absolute_length_max).is_err()
         {
             return Ok(chunks);
         }
 
+        chunks
+            .iter_mut()
+            .for_each(|chunk| chunk.core_range = Some(chunk.base_text_range()));
         let mut chunker = Self {
             config: Arc::clone(config),
             chunks_copy: chunks.clone(),
             chunks,
-            overlap_percent,
+            overlap,
         };
 
         for i in 0..chunker.chunks.len() {
-            if chunker.config.chunks_found.load(Ordering::Relaxed) {
-                return Err(anyhow!(
-                    "OverlapChunker stopping early due to chunks_found in other thread."
-                ));
+            chunker.config.chunks_found.check()?;
+            match overlap {
+                OverlapStrategy::Sentences(count) => {
+                    chunker.unit_overlap(i, &Separator::SentencesRuleBased, count)
+                }
+                OverlapStrategy::Paragraphs(count) => {
+                    chunker.unit_overlap(i, &Separator::TwoPlusEoL, count)
+                }
+                OverlapStrategy::Percent(_) | OverlapStrategy::Tokens(_) => {
+                    let (back_min, back_max, for_min, for_max) = chunker.overlap_lengths(i);
+                    chunker.forward_overlap(i, for_min, for_max)?;
+                    chunker.backward_overlap(i, back_min, back_max)?;
+                }
             }
-            let (back_min, back_max, for_min, for_max) = chunker.overlap_lengths(i);
-            chunker.forward_overlap(i, for_min, for_max)?;
-            chunker.backward_overlap(i, back_min, back_max)?;
         }
 
         Ok(chunker.chunks)
     }
 
+    /// Adds up to `count` whole units of `unit`, such as sentences, from each neighboring chunk, nearest first.
+    /// Stops at the first unit that would put the chunk over `absolute_length_max`, so overlap is always whole units.
+    fn unit_overlap(&mut self, chunk_index: usize, unit: &Separator, count: usize) {
+        if chunk_index + 1 < self.chunks.len() {
+            let units = self.units(chunk_index + 1, unit);
+            self.add_units(chunk_index, units.into_iter().take(count), false);
+        }
+        if chunk_index > 0 {
+            let units = self.units(chunk_index - 1, unit);
+            self.add_units(chunk_index, units.into_iter().rev().take(count), true);
+        }
+    }
+
+    /// The original text of a chunk split into units of `unit`.
+    fn units(&self, chunk_index: usize, unit: &Separator) -> Vec<TextSplit> {
+        let range = self.chunks_copy[chunk_index].base_text_range();
+        let base_text = &self.config.base_text;
+        let text = &base_text[range.clone()];
+        let unit_indices = unit.split_text_into_indices(text);
+        // Overlap never starts or ends inside a protected span.
+        let unit_indices = match &self.config.protected_spans {
+            Some(protected_spans) => protected_spans.keep_whole(text, unit_indices),
+            None => unit_indices,
+        };
+        let mut units: Vec<TextSplit> = unit_indices
+            .into_iter()
+            .map(|indices| TextSplit {
+                indices: range.start + indices.start..range.start + indices.end,
+                split_separator: unit.clone(),
+                base_text: Arc::clone(base_text),
+                token_count: None,
+                protected_spans: self.config.protected_spans.clone(),
+                separators: self.config.separators.clone(),
+            })
+            .collect();
+        self.config.tile_splits(&mut units, &range);
+        units
+    }
+
+    fn add_units(
+        &mut self,
+        chunk_index: usize,
+        units: impl Iterator<Item = TextSplit>,
+        backwards: bool,
+    ) {
+        let absolute_length_max = self.config.absolute_length_max as f32;
+        let chunk = &mut self.chunks[chunk_index];
+        for unit in units {
+            chunk.add_split(unit, backwards);
+            if chunk.exact_token_count() > absolute_length_max {
+                chunk.remove_split(backwards);
+                break;
+            }
+        }
+    }
+
     fn overlap_lengths(&mut self, chunk_index: usize) -> (f32, f32, f32, f32) {
         let chunk = self.chunks.get_mut(chunk_index).unwrap();
 
         let token_count = chunk.token_count(false);
-        let overlap_length = ((token_count * self.overlap_percent) + token_count).ceil();
+        let overlap_length = (self.overlap.overlap_tokens(token_count) + token_count).ceil();
 
         if chunk_index == 0 {
             let overlap_length_min =
@@ -106,7 +237,7 @@ impl OverlapChunker {
         chunk_index: usize,
         length_min: f32,
         length_max: f32,
-    ) -> Result<()> {
+    ) -> Result<(), ChunkerError> {
         if chunk_index == self.chunks.len() - 1 {
             return Ok(());
         };
@@ -135,7 +266,7 @@ impl OverlapChunker {
         chunk_index: usize,
         length_min: f32,
         length_max: f32,
-    ) -> Result<()> {
+    ) -> Result<(), ChunkerError> {
         if chunk_index == 0 {
             return Ok(());
         };
@@ -161,15 +292,11 @@ impl OverlapChunker {
         length_max: f32,
         estimated: bool,
         backwards: bool,
-    ) -> Result<()> {
+    ) -> Result<(), ChunkerError> {
         let chunk = &mut self.chunks[chunk_index];
 
         loop {
-            if self.config.chunks_found.load(Ordering::Relaxed) {
-                return Err(anyhow!(
-                    "OverlapChunker stopping early due to chunks_found in other thread."
-                ));
-            }
+            self.config.chunks_found.check()?;
             while chunk.token_count(estimated).ceil() <= length_min && !splits.is_empty() {
                 let split = if backwards {
                     splits.pop_back().unwrap()
@@ -191,10 +318,16 @@ impl OverlapChunker {
             if chunk.token_count(estimated).ceil() >= length_min
                 && chunk.token_count(estimated).ceil() <= length_max
             {
-                return Ok(());
+                // Counted incrementally, so check the chunk once against the tokenizer. If it is over, keep building with full counts.
+                if estimated || chunk.verify_token_count(length_max) {
+                    return Ok(());
+                }
+                continue;
             }
             if splits.is_empty() {
-                return Err(anyhow!("overlap error: splits is empty, but chunk token count is not within min and max."));
+                return Err(self.config.impossible(
+                    "overlap error: no splits remain, but the chunk's token count is not within min and max",
+                ));
             }
             let split = if backwards {
                 splits.pop_back().unwrap()
@@ -202,8 +335,7 @@ impl OverlapChunker {
                 splits.pop_front().unwrap()
             };
             self.config
-                .split_split(split)
-                .ok_or_else(|| anyhow!("split_split returned None for while generating overlap."))?
+                .split_split(split)?
                 .into_iter()
                 .rev()
                 .for_each(|split| {
@@ -285,4 +417,56 @@ mod tests {
             .unwrap();
         assert!(res.token_counts().iter().all(|&x| x <= absolute_length_max));
     }
+
+    #[test]
+    fn strategies() {
+        let incoming_text = &test_text::TEXT.long.content;
+        let absolute_length_max = 256;
+        for overlap in [
+            OverlapStrategy::Tokens(32),
+            OverlapStrategy::Sentences(2),
+            OverlapStrategy::Paragraphs(1),
+        ] {
+            let mut res = TextChunker::new()
+                .unwrap()
+                .max_chunk_token_size(absolute_length_max)
+                .overlap(overlap)
+                .run_return_result(incoming_text)
+                .unwrap();
+            assert!(res.token_counts().iter().all(|&x| x <= absolute_length_max));
+        }
+
+        // Sentence overlap starts each chunk at a sentence of the previous chunk.
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(absolute_length_max)
+            .overlap(OverlapStrategy::Sentences(2))
+            .run_return_result(incoming_text)
+            .unwrap();
+        for pair in res.chunks.windows(2) {
+            let previous_core = pair[0].core_base_text_range();
+            let start = pair[1].base_text_range().start;
+            if start < pair[1].core_base_text_range().start {
+                let sentences = Separator::SentencesRuleBased
+                    .split_text_into_indices(&res.base_text()[previous_core.clone()]);
+                assert!(sentences
+                    .iter()
+                    .any(|sentence| previous_core.start + sentence.start == start));
+            }
+        }
+
+        for overlap in [OverlapStrategy::Tokens(2000), OverlapStrategy::Percent(1.5)] {
+            assert!(matches!(
+                TextChunker::new().unwrap().overlap(overlap).run(incoming_text),
+                Err(ChunkerError::InvalidConfig(_))
+            ));
+        }
+        assert!(matches!(
+            TextChunker::new()
+                .unwrap()
+                .overlap_percent(1.5)
+                .run(incoming_text),
+            Err(ChunkerError::InvalidConfig(_))
+        ));
+    }
 }
@@ -0,0 +1,329 @@
+use super::*;
+use crate::concatenator::TextConcatenator;
+
+/// The most cells, chunks times budget, of the bitset table [`PackingStrategy::Knapsack`] solves.
+const KNAPSACK_CELLS_MAX: usize = 1 << 26;
+
+/// How a [`ContextPacker`] chooses which chunks fit in the token budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackingStrategy {
+    /// Takes chunks from the highest score down, skipping any that no longer fit.
+    #[default]
+    Greedy,
+    /// Takes the set of chunks with the highest total score that fits. Chunks with a score of 0.0 or less are never taken.
+    /// Solved exactly by dynamic programming over the budget, so its cost grows with the number of chunks times the token budget.
+    /// Above [`KNAPSACK_CELLS_MAX`] chunks times budget it falls back to [`PackingStrategy::Greedy`].
+    Knapsack,
+    /// Takes chunks in the order they were given, skipping any that no longer fit. Scores are ignored.
+    DocumentOrder,
+}
+
+/// The chunks packed into a prompt by a [`ContextPacker`]. Indices are positions in the chunks given to the packer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackedContext {
+    /// The selected chunks joined with the packer's [`TextConcatenator`].
+    pub text: String,
+    /// The size of `text`, measured by the packer's sizer. Never more than the token budget.
+    pub token_count: u32,
+    /// The indices of the selected chunks, in the order they appear in `text`.
+    pub selected: Vec<usize>,
+    /// The indices of the chunks that were left out, in ascending order.
+    pub dropped: Vec<usize>,
+}
+
+impl PackedContext {
+    /// Whether the chunk at `index` was left out.
+    pub fn is_dropped(&self, index: usize) -> bool {
+        self.dropped.binary_search(&index).is_ok()
+    }
+}
+
+/// Packs scored chunks, such as retrieval results, into a prompt's token budget.
+/// The chunks are joined with a [`TextConcatenator`], and the joined text is measured, so separator tokens count against the budget.
+///
+/// ```ignore
+/// let packed = ContextPacker::new(2048)?
+///     .strategy(PackingStrategy::Knapsack)
+///     .concatenator(TextConcatenator::DoubleNewline)
+///     .pack(&[("First chunk", 0.9), ("Second chunk", 0.4)]);
+/// ```
+pub struct ContextPacker {
+    /// Measures chunk sizes. Defaults to the TikToken tokenizer.
+    sizer: Arc<dyn ChunkSizer>,
+    /// Inclusive limit on the size of the joined text.
+    token_budget: u32,
+    /// Default is [`PackingStrategy::Greedy`].
+    strategy: PackingStrategy,
+    /// Joins the selected chunks. Default is [`TextConcatenator::DoubleNewline`].
+    concatenator: TextConcatenator,
+    /// Whether to order the selected chunks as they were given rather than by score. Default is false.
+    keep_document_order: bool,
+}
+
+impl ContextPacker {
+    /// Creates a new instance of the [`ContextPacker`] struct using the default TikToken tokenizer.
+    ///
+    /// * `token_budget` - The maximum token size of the packed text. Inclusive.
+    pub fn new(token_budget: u32) -> Result<Self, ChunkerError> {
+        Self::new_with_sizer(
+            Arc::new(
+                LlmTokenizer::new_tiktoken(TOKENIZER_TIKTOKEN_DEFAULT)
+                    .map_err(|e| ChunkerError::Tokenizer(e.into()))?,
+            ),
+            token_budget,
+        )
+    }
+
+    /// Creates a new instance of the [`ContextPacker`] struct using a custom tokenizer, such as the tokenizer of the model the prompt is for.
+    pub fn new_with_tokenizer(
+        custom_tokenizer: &Arc<LlmTokenizer>,
+        token_budget: u32,
+    ) -> Result<Self, ChunkerError> {
+        Self::new_with_sizer(custom_tokenizer.clone(), token_budget)
+    }
+
+    /// Creates a new instance of the [`ContextPacker`] struct that sizes text with a [`ChunkSizer`]. The budget is then in the sizer's units.
+    pub fn new_with_sizer(
+        sizer: Arc<dyn ChunkSizer>,
+        token_budget: u32,
+    ) -> Result<Self, ChunkerError> {
+        if token_budget == 0 {
+            return Err(ChunkerError::InvalidConfig(
+                "token_budget must be greater than 0".to_string(),
+            ));
+        }
+        Ok(Self {
+            sizer,
+            token_budget,
+            strategy: PackingStrategy::default(),
+            concatenator: TextConcatenator::DoubleNewline,
+            keep_document_order: false,
+        })
+    }
+
+    /// Sets how chunks are chosen. See [`PackingStrategy`].
+    pub fn strategy(mut self, strategy: PackingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the [`TextConcatenator`] the selected chunks are joined with. Default is [`TextConcatenator::DoubleNewline`].
+    pub fn concatenator(mut self, concatenator: TextConcatenator) -> Self {
+        self.concatenator = concatenator;
+        self
+    }
+
+    /// Orders the selected chunks as they were given, rather than from the highest score down. Default is false.
+    /// [`PackingStrategy::DocumentOrder`] always keeps the given order.
+    pub fn keep_document_order(mut self, keep_document_order: bool) -> Self {
+        self.keep_document_order = keep_document_order;
+        self
+    }
+
+    /// Packs `(text, score)` pairs into the token budget. Chunks with equal scores keep their given order.
+    pub fn pack<T: AsRef<str>>(&self, chunks: &[(T, f32)]) -> PackedContext {
+        let texts: Vec<&str> = chunks.iter().map(|(text, _)| text.as_ref()).collect();
+        let scores: Vec<f32> = chunks.iter().map(|(_, score)| *score).collect();
+        self.pack_texts(&texts, &scores)
+    }
+
+    /// Packs the chunks of a [`ChunkerResult`] into the token budget. Indices are positions in [`ChunkerResult::chunks`].
+    ///
+    /// * `scores` - The score of each chunk. If None, every chunk scores the same, so chunks are taken in order.
+    pub fn pack_result(&self, result: &ChunkerResult, scores: Option<&[f32]>) -> PackedContext {
+        let texts: Vec<String> = result.chunks.iter().map(Chunk::joined_text).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let scores = match scores {
+            Some(scores) => scores.to_vec(),
+            None => vec![1.0; texts.len()],
+        };
+        self.pack_texts(&texts, &scores)
+    }
+
+    fn pack_texts(&self, texts: &[&str], scores: &[f32]) -> PackedContext {
+        // Chunks without a score are never selected.
+        let count = texts.len().min(scores.len());
+        let separator_size = self.sizer.size(self.concatenator.as_str());
+        let sizes: Vec<u32> = texts[..count]
+            .par_iter()
+            .map(|text| self.sizer.size(text).saturating_add(separator_size))
+            .collect();
+        // Every chunk after the first adds a separator, so the budget allows for one more than are used.
+        let budget = self.token_budget.saturating_add(separator_size);
+
+        let mut by_score: Vec<usize> = (0..count).collect();
+        by_score.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        let mut selected = match self.strategy {
+            PackingStrategy::Greedy => take_while_fits(by_score.iter().copied(), &sizes, budget),
+            PackingStrategy::DocumentOrder => take_while_fits(0..count, &sizes, budget),
+            PackingStrategy::Knapsack => {
+                let mut selected =
+                    knapsack(&sizes, &scores[..count], budget).unwrap_or_else(|| {
+                        let positive = by_score.iter().copied().filter(|i| scores[*i] > 0.0);
+                        take_while_fits(positive, &sizes, budget)
+                    });
+                selected.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+                selected
+            }
+        };
+        if self.keep_document_order || self.strategy == PackingStrategy::DocumentOrder {
+            selected.sort_unstable();
+        }
+
+        // Joined text can measure more than the sum of its parts, so the lowest scoring chunk is dropped until the measured text fits.
+        let (text, token_count) = loop {
+            let text = selected
+                .iter()
+                .map(|i| texts[*i])
+                .collect::<Vec<_>>()
+                .join(self.concatenator.as_str());
+            let token_count = self.sizer.size(&text);
+            if token_count <= self.token_budget || selected.is_empty() {
+                break (text, token_count);
+            }
+            let lowest = selected
+                .iter()
+                .enumerate()
+                .rev()
+                .min_by(|(_, a), (_, b)| scores[**a].total_cmp(&scores[**b]))
+                .map(|(position, _)| position)
+                .unwrap();
+            selected.remove(lowest);
+        };
+
+        let mut is_selected = vec![false; texts.len()];
+        selected.iter().for_each(|i| is_selected[*i] = true);
+        let dropped = (0..texts.len()).filter(|i| !is_selected[*i]).collect();
+        PackedContext {
+            text,
+            token_count,
+            selected,
+            dropped,
+        }
+    }
+}
+
+/// Takes the chunks in `order` that fit in what is left of `budget`.
+fn take_while_fits(order: impl Iterator<Item = usize>, sizes: &[u32], budget: u32) -> Vec<usize> {
+    let mut remaining = budget;
+    order
+        .filter(|i| {
+            let fits = sizes[*i] <= remaining;
+            if fits {
+                remaining -= sizes[*i];
+            }
+            fits
+        })
+        .collect()
+}
+
+/// The 0/1 knapsack: the indices of the chunks with the highest total score whose sizes sum to at most `budget`, in ascending order.
+/// None if the table would have more than [`KNAPSACK_CELLS_MAX`] cells.
+fn knapsack(sizes: &[u32], scores: &[f32], budget: u32) -> Option<Vec<usize>> {
+    let capacity = budget as usize;
+    if sizes.len().saturating_mul(capacity + 1) > KNAPSACK_CELLS_MAX {
+        return None;
+    }
+    // Whether chunk `i` is taken at capacity `c` is bit `c` of row `i`.
+    let row_words = (capacity + 1).div_ceil(u64::BITS as usize);
+    let mut best = vec![0.0f32; capacity + 1];
+    let mut taken = vec![0u64; sizes.len() * row_words];
+    for (i, size) in sizes.iter().enumerate() {
+        let size = *size as usize;
+        if scores[i] <= 0.0 || size > capacity {
+            continue;
+        }
+        for c in (size..=capacity).rev() {
+            let with = best[c - size] + scores[i];
+            if with > best[c] {
+                best[c] = with;
+                taken[i * row_words + c / 64] |= 1 << (c % 64);
+            }
+        }
+    }
+    let mut selected = Vec::new();
+    let mut c = capacity;
+    for i in (0..sizes.len()).rev() {
+        if taken[i * row_words + c / 64] >> (c % 64) & 1 == 1 {
+            selected.push(i);
+            c -= sizes[i] as usize;
+        }
+    }
+    selected.reverse();
+    Some(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn strategies() {
+        let chunks = [
+            ("one two three four five six seven eight", 0.5),
+            ("alpha beta gamma", 0.9),
+            ("red green blue yellow", 0.8),
+            ("north south", 0.1),
+        ];
+        let packer = ContextPacker::new_with_sizer(Arc::new(WordSizer), 9)
+            .unwrap()
+            .concatenator(TextConcatenator::Space);
+
+        let packed = packer.pack(&chunks);
+        assert_eq!(packed.selected, vec![1, 2, 3]);
+        assert_eq!(packed.dropped, vec![0]);
+        assert_eq!(packed.text, "alpha beta gamma red green blue yellow north south");
+        assert_eq!(packed.token_count, 9);
+
+        let packed = packer.keep_document_order(true).pack(&chunks);
+        assert_eq!(packed.selected, vec![1, 2, 3]);
+
+        // Taking the highest score first leaves no room for the two shorter chunks, which are worth more together.
+        let chunks = [("g h i", 1.0), ("a b c d e f", 1.5), ("j k l", 0.9)];
+        let packer = ContextPacker::new_with_sizer(Arc::new(WordSizer), 7).unwrap();
+        assert_eq!(packer.pack(&chunks).selected, vec![1]);
+        let packer = packer.strategy(PackingStrategy::Knapsack);
+        assert_eq!(packer.pack(&chunks).selected, vec![0, 2]);
+        let packer = packer.strategy(PackingStrategy::DocumentOrder);
+        let packed = packer.pack(&chunks);
+        assert_eq!(packed.selected, vec![0, 2]);
+        assert!(packed.is_dropped(1));
+
+        // Tables larger than the limit are not solved, and the packer falls back to greedy.
+        assert_eq!(knapsack(&[4, 3, 3], &[1.0, 1.5, 0.9], 7), Some(vec![0, 1]));
+        assert_eq!(
+            knapsack(&[1; 2], &[1.0; 2], KNAPSACK_CELLS_MAX as u32),
+            None
+        );
+
+        assert!(matches!(
+            ContextPacker::new_with_sizer(Arc::new(WordSizer), 0),
+            Err(ChunkerError::InvalidConfig(_))
+        ));
+        // A budget near the maximum leaves room for the separator without overflowing.
+        let packer = ContextPacker::new_with_sizer(Arc::new(WordSizer), u32::MAX).unwrap();
+        assert_eq!(packer.pack(&chunks).selected.len(), 3);
+    }
+
+    #[test]
+    fn chunker_result() {
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .run_return_result(&CHUNK_TESTS.chunking_small.content)
+            .unwrap();
+        for strategy in [
+            PackingStrategy::Greedy,
+            PackingStrategy::Knapsack,
+            PackingStrategy::DocumentOrder,
+        ] {
+            let packer = ContextPacker::new(300).unwrap().strategy(strategy);
+            let packed = packer.pack_result(&res, None);
+            assert!(packed.token_count <= 300);
+            assert!(!packed.selected.is_empty());
+            assert!(!packed.dropped.is_empty());
+            assert_eq!(packed.selected.len() + packed.dropped.len(), res.chunks.len());
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use super::*;
+use llm_models::{local_model::gguf::preset::LlmPreset, LlmModelBase};
+
+/// The largest chunk for an embedding model. Longer chunks dilute the embedding of any one passage, even when the model accepts them.
+const EMBEDDING_CHUNK_MAX: u32 = 512;
+/// A RAG context chunk is this fraction of the model's context, so several retrieved chunks, the instructions and the answer fit together.
+const RAG_CONTEXT_CHUNK_RATIO: f32 = 1.0 / 16.0;
+const RAG_CONTEXT_CHUNK_MIN: u32 = 128;
+const RAG_CONTEXT_CHUNK_MAX: u32 = 1024;
+/// A summarization map chunk is this fraction of the model's context, leaving the rest for the prompt and the summary.
+const SUMMARIZATION_MAP_CHUNK_RATIO: f32 = 0.5;
+const SUMMARIZATION_MAP_CHUNK_MIN: u32 = 256;
+const SUMMARIZATION_MAP_CHUNK_MAX: u32 = 16384;
+/// The minimum chunk size of the retrieval uses, as a fraction of the maximum. Lower than the default, so chunks can end on natural boundaries.
+const RETRIEVAL_CHUNK_MIN_RATIO: f32 = 0.5;
+
+/// What the chunks will be used for, which decides their size and overlap in [`TextChunker::for_model`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkingPurpose {
+    /// Chunks embedded by an embedding model for retrieval. At most 512 tokens or the model's context, with 10% overlap.
+    Embedding,
+    /// Chunks retrieved into the prompt of a generation model. One sixteenth of the model's context, between 128 and 1024 tokens, with one sentence of overlap.
+    RagContext,
+    /// Chunks each summarized on their own in the map step of a map-reduce summary. Half the model's context, between 256 and 16384 tokens, without overlap.
+    SummarizationMap,
+}
+
+impl ChunkingPurpose {
+    /// The maximum chunk size for a model with a context of `context_length` tokens. Never more than the context.
+    fn max_chunk_token_size(&self, context_length: u64) -> u32 {
+        let context_length = context_length.min(u32::MAX as u64) as u32;
+        let size = match self {
+            Self::Embedding => EMBEDDING_CHUNK_MAX,
+            Self::RagContext => ((context_length as f32 * RAG_CONTEXT_CHUNK_RATIO) as u32)
+                .clamp(RAG_CONTEXT_CHUNK_MIN, RAG_CONTEXT_CHUNK_MAX),
+            Self::SummarizationMap => ((context_length as f32 * SUMMARIZATION_MAP_CHUNK_RATIO)
+                as u32)
+                .clamp(SUMMARIZATION_MAP_CHUNK_MIN, SUMMARIZATION_MAP_CHUNK_MAX),
+        };
+        size.min(context_length)
+    }
+
+    fn overlap(&self) -> Option<OverlapStrategy> {
+        match self {
+            Self::Embedding => Some(OverlapStrategy::Percent(0.1)),
+            Self::RagContext => Some(OverlapStrategy::Sentences(1)),
+            Self::SummarizationMap => None,
+        }
+    }
+
+    fn min_chunk_token_size(&self, max_chunk_token_size: u32) -> Option<u32> {
+        match self {
+            Self::Embedding | Self::RagContext => {
+                Some((max_chunk_token_size as f32 * RETRIEVAL_CHUNK_MIN_RATIO) as u32)
+            }
+            Self::SummarizationMap => None,
+        }
+    }
+}
+
+impl TextChunker {
+    /// Creates a [`TextChunker`] for a model from `llm_models`, using the model's tokenizer, with chunk sizes and overlap derived from its context size and `purpose`.
+    /// Settings can still be changed with the builder methods afterwards.
+    pub fn for_model(model: &LlmModelBase, purpose: ChunkingPurpose) -> Self {
+        Self::for_context(&model.tokenizer, model.model_ctx_size, purpose)
+    }
+
+    /// Loads an `llm_models` preset and creates a [`TextChunker`] for it. See [`TextChunker::for_model`].
+    pub fn for_preset(preset: LlmPreset, purpose: ChunkingPurpose) -> Result<Self, ChunkerError> {
+        let model = preset
+            .load()
+            .map_err(|e| ChunkerError::Tokenizer(e.into()))?;
+        Ok(Self::for_model(&model.model_base, purpose))
+    }
+
+    /// Creates a [`TextChunker`] for any model, from its tokenizer and its context size in tokens. See [`TextChunker::for_model`].
+    pub fn for_context(
+        tokenizer: &Arc<LlmTokenizer>,
+        context_length: u64,
+        purpose: ChunkingPurpose,
+    ) -> Self {
+        let max_chunk_token_size = purpose.max_chunk_token_size(context_length);
+        let mut chunker =
+            Self::new_with_tokenizer(tokenizer).max_chunk_token_size(max_chunk_token_size);
+        if let Some(min_chunk_token_size) = purpose.min_chunk_token_size(max_chunk_token_size) {
+            chunker = chunker.min_chunk_token_size(min_chunk_token_size);
+        }
+        if let Some(overlap) = purpose.overlap() {
+            chunker = chunker.overlap(overlap);
+        }
+        chunker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn purposes() {
+        assert_eq!(ChunkingPurpose::Embedding.max_chunk_token_size(8192), 512);
+        assert_eq!(ChunkingPurpose::Embedding.max_chunk_token_size(256), 256);
+        assert_eq!(ChunkingPurpose::RagContext.max_chunk_token_size(8192), 512);
+        assert_eq!(ChunkingPurpose::RagContext.max_chunk_token_size(128_000), 1024);
+        assert_eq!(ChunkingPurpose::SummarizationMap.max_chunk_token_size(8192), 4096);
+        assert_eq!(ChunkingPurpose::SummarizationMap.max_chunk_token_size(128_000), 16384);
+
+        let tokenizer = Arc::new(LlmTokenizer::new_tiktoken(TOKENIZER_TIKTOKEN_DEFAULT).unwrap());
+        for purpose in [
+            ChunkingPurpose::Embedding,
+            ChunkingPurpose::RagContext,
+            ChunkingPurpose::SummarizationMap,
+        ] {
+            let max = purpose.max_chunk_token_size(4096);
+            let mut res = TextChunker::for_context(&tokenizer, 4096, purpose)
+                .run_return_result(&CHUNK_TESTS.chunking_small.content)
+                .unwrap();
+            assert!(res.token_counts().iter().all(|count| *count <= max));
+        }
+    }
+
+    #[test]
+    fn preset() {
+        let chunker =
+            TextChunker::for_preset(LlmPreset::Llama3_1_8bInstruct, ChunkingPurpose::RagContext)
+                .unwrap();
+        assert!(chunker.absolute_length_max <= RAG_CONTEXT_CHUNK_MAX);
+        assert!(chunker.run(&CHUNK_TESTS.chunking_small.content).is_ok());
+    }
+}
@@ -0,0 +1,159 @@
+use super::*;
+use std::time::Duration;
+
+/// Describes how a [`ChunkerResult`] was produced: the [`Separator`] attempts, the chunker that succeeded, tokenizer usage, and timings.
+#[derive(Clone, Debug)]
+pub struct ChunkingReport {
+    /// Every [`Separator`] attempted, in priority order.
+    pub attempts: Vec<SeparatorAttempt>,
+    /// The index in `attempts` of the attempt whose chunks were returned.
+    pub selected: usize,
+    /// The wall time of the whole run, including every attempt.
+    pub total_duration: Duration,
+}
+
+impl ChunkingReport {
+    /// The attempt whose chunks were returned.
+    pub fn selected_attempt(&self) -> &SeparatorAttempt {
+        &self.attempts[self.selected]
+    }
+
+    /// The [`Separator`] the returned chunks were split on.
+    pub fn separator(&self) -> &Separator {
+        &self.selected_attempt().separator
+    }
+
+    /// The chunker that built the returned chunks.
+    pub fn chunker(&self) -> Option<ChunkerKind> {
+        match self.selected_attempt().outcome {
+            AttemptOutcome::Succeeded(chunker) => Some(chunker),
+            _ => None,
+        }
+    }
+
+    /// The per-phase timings of the attempt whose chunks were returned.
+    pub fn phase_durations(&self) -> &PhaseDurations {
+        &self.selected_attempt().phase_durations
+    }
+
+    /// The number of tokenizer calls made across all attempts.
+    pub fn tokenizer_calls(&self) -> usize {
+        self.attempts
+            .iter()
+            .map(|attempt| attempt.tokenizer_calls)
+            .sum()
+    }
+}
+
+/// A single chunking attempt starting from one [`Separator`].
+#[derive(Clone, Debug)]
+pub struct SeparatorAttempt {
+    pub separator: Separator,
+    pub outcome: AttemptOutcome,
+    /// The number of tokenizer calls made by this attempt on whole texts, splits, and chunks.
+    pub tokenizer_calls: usize,
+    /// The number of tokenizer calls made by this attempt on the short windows around the junctions between splits, used to count chunks incrementally.
+    pub boundary_tokenizer_calls: usize,
+    /// Timings of the phases this attempt reached.
+    pub phase_durations: PhaseDurations,
+    pub duration: Duration,
+}
+
+impl SeparatorAttempt {
+    pub(super) fn new(separator: &Separator) -> Self {
+        Self {
+            separator: separator.clone(),
+            outcome: AttemptOutcome::Cancelled,
+            tokenizer_calls: 0,
+            boundary_tokenizer_calls: 0,
+            phase_durations: PhaseDurations::default(),
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttemptOutcome {
+    /// Valid chunks were built by the chunker.
+    Succeeded(ChunkerKind),
+    /// Valid chunks could not be built, for the given reason.
+    Failed(String),
+    /// The attempt stopped early because chunks from another attempt were used instead.
+    Cancelled,
+}
+
+/// The chunkers the [`TextChunker`] uses to build chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkerKind {
+    /// The text fit within a single chunk.
+    SingleChunk,
+    /// The [`DfsTextChunker`].
+    Dfs,
+    /// The [`LinearChunker`].
+    Linear,
+    /// Semantic chunking with an [`Embedder`], see [`TextChunker::semantic`].
+    Semantic,
+}
+
+/// Time spent in each phase of a chunking attempt.
+#[derive(Clone, Debug, Default)]
+pub struct PhaseDurations {
+    /// Cleaning the incoming text.
+    pub cleaning: Duration,
+    /// Splitting the cleaned text and counting the tokens of the splits.
+    pub splitting: Duration,
+    /// Building chunks from the splits.
+    pub chunking: Duration,
+    /// Adding overlap to the chunks.
+    pub overlap: Duration,
+}
+
+/// Wraps the [`ChunkSizer`] to count how many times it is called during an attempt.
+#[derive(Clone)]
+pub(super) struct TokenCounter {
+    sizer: Arc<dyn ChunkSizer>,
+    calls: Arc<AtomicUsize>,
+    boundary_calls: Arc<AtomicUsize>,
+}
+
+impl TokenCounter {
+    pub(super) fn new(sizer: Arc<dyn ChunkSizer>) -> Self {
+        Self {
+            sizer,
+            calls: Arc::new(AtomicUsize::new(0)),
+            boundary_calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub(super) fn count_tokens(&self, text: &str) -> u32 {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.sizer.size(text)
+    }
+
+    /// Counts the tokens of a short window around a junction between splits.
+    pub(super) fn count_boundary_tokens(&self, text: &str) -> u32 {
+        self.boundary_calls.fetch_add(1, Ordering::Relaxed);
+        self.sizer.size(text)
+    }
+
+    pub(super) fn estimate_split_size(
+        &self,
+        split_size: u32,
+        separator: &Separator,
+        previous: Option<&Separator>,
+    ) -> f32 {
+        self.sizer.estimate_split_size(split_size, separator, previous)
+    }
+
+    pub(super) fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn boundary_calls(&self) -> usize {
+        self.boundary_calls.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn sizer(&self) -> Arc<dyn ChunkSizer> {
+        Arc::clone(&self.sizer)
+    }
+}
@@ -0,0 +1,327 @@
+use super::*;
+use anyhow::anyhow;
+
+/// The number of sentences on each side of a candidate boundary that are averaged and compared.
+pub(super) const SEMANTIC_WINDOW_DEFAULT: usize = 2;
+/// Boundaries are placed where the dissimilarity between adjacent windows is above this percentile of all candidate boundaries.
+pub(super) const SEMANTIC_BREAKPOINT_PERCENTILE_DEFAULT: f32 = 0.9;
+
+/// Embeds text into vectors for semantic chunking. See [`TextChunker::semantic`].
+/// Implement this with any embedding model. Texts on the same topic should have a high cosine similarity.
+pub trait Embedder: Send + Sync {
+    /// Embeds each text into a vector. Returns one vector per text, in order, and all vectors must have the same length.
+    fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>>;
+}
+
+impl TextChunker {
+    /// Backend runner for semantic chunking.
+    /// Splits the text into sentences, embeds them, and splits the text into segments where the similarity of adjacent windows of sentences drops.
+    /// Larger segments are chunked like any other text, and only the overlap of a chunk spans two segments.
+    pub(super) fn semantic_chunker(
+        &self,
+        incoming_text: &str,
+        embedder: &Arc<dyn Embedder>,
+        sizer: Arc<dyn ChunkSizer>,
+        absolute_length_max: u32,
+        chunking_start_time: std::time::Instant,
+    ) -> Result<ChunkerResult, ChunkerError> {
+        let attempt_start_time = std::time::Instant::now();
+        let separator = Separator::SentencesRuleBased;
+        let mut attempt = SeparatorAttempt::new(&separator);
+        let tokenizer = TokenCounter::new(sizer);
+        let mut config = ChunkerConfig::new(
+            &ChunksFound::new(true).interruptible(
+                self.cancellation_token.clone(),
+                self.deadline(chunking_start_time),
+            ),
+            separator.clone(),
+            incoming_text,
+            absolute_length_max,
+            self.absolute_length_min,
+            self.overlap,
+            self.text_mode,
+            self.protected_spans.clone(),
+            self.separators.clone(),
+            tokenizer.clone(),
+        )?;
+        let mut kind = ChunkerKind::Semantic;
+        if config.initial_separator == Separator::None {
+            // The text fits in a single chunk, but is still split where the topic changes, unless it is a single sentence.
+            config.initial_separator = separator;
+            match config.split_base_text() {
+                Ok(splits) if splits.len() > 1 => config.initial_splits = splits,
+                _ => {
+                    config.initial_separator = Separator::None;
+                    kind = ChunkerKind::SingleChunk;
+                }
+            }
+        }
+        attempt.phase_durations = config.phase_durations.clone();
+        let config = Arc::new(config);
+
+        let chunks = if kind == ChunkerKind::SingleChunk {
+            let text = match self.text_mode {
+                TextMode::LosslessTrimmed => incoming_text.trim(),
+                _ => incoming_text,
+            };
+            vec![Chunk::dummy_chunk(&config, text)]
+        } else {
+            let phase_start_time = std::time::Instant::now();
+            let segments = self.semantic_segments(&config, embedder)?;
+            attempt.phase_durations.chunking += phase_start_time.elapsed();
+            let mut chunks = Vec::new();
+            for segment in segments {
+                chunks.extend(self.chunk_segment(
+                    &config,
+                    segment,
+                    &mut attempt.phase_durations,
+                )?);
+            }
+            // Overlap is added across the whole text, so it can reach into the neighboring segments.
+            let phase_start_time = std::time::Instant::now();
+            let chunks = OverlapChunker::run(&config, chunks)?;
+            attempt.phase_durations.overlap += phase_start_time.elapsed();
+            chunks
+        };
+
+        attempt.outcome = AttemptOutcome::Succeeded(kind);
+        attempt.tokenizer_calls = tokenizer.calls();
+        attempt.boundary_tokenizer_calls = tokenizer.boundary_calls();
+        attempt.duration = attempt_start_time.elapsed();
+        let mut result = ChunkerResult::new(incoming_text, &config, chunking_start_time, chunks);
+        result.report = Some(ChunkingReport {
+            attempts: vec![attempt],
+            selected: 0,
+            total_duration: chunking_start_time.elapsed(),
+        });
+        result.sizers = self.sizers();
+        Ok(result)
+    }
+
+    /// Groups the initial sentence splits into segments of consecutive sentences on the same topic.
+    fn semantic_segments(
+        &self,
+        config: &Arc<ChunkerConfig>,
+        embedder: &Arc<dyn Embedder>,
+    ) -> Result<Vec<VecDeque<TextSplit>>, ChunkerError> {
+        let texts: Vec<&str> = config
+            .initial_splits
+            .iter()
+            .map(|split| split.text())
+            .collect();
+        let embeddings = embedder.embed(&texts).map_err(ChunkerError::Embedder)?;
+        if embeddings.len() != texts.len() {
+            return Err(ChunkerError::Embedder(anyhow!(
+                "expected {} embeddings, but the embedder returned {}",
+                texts.len(),
+                embeddings.len()
+            )));
+        }
+        let mut breakpoints = semantic_breakpoints(
+            &embeddings,
+            self.semantic_window,
+            self.semantic_breakpoint_percentile,
+        )
+        .into_iter()
+        .peekable();
+
+        let mut segments = vec![VecDeque::new()];
+        for (i, split) in config.initial_splits.iter().enumerate() {
+            if breakpoints.next_if_eq(&i).is_some() {
+                segments.push(VecDeque::new());
+            }
+            segments.last_mut().unwrap().push_back(split.clone());
+        }
+        Ok(segments)
+    }
+
+    /// Builds the chunks of a single segment, without overlap.
+    fn chunk_segment(
+        &self,
+        config: &Arc<ChunkerConfig>,
+        segment: VecDeque<TextSplit>,
+        phase_durations: &mut PhaseDurations,
+    ) -> Result<Vec<Chunk>, ChunkerError> {
+        let mut chunk = Chunk::new(config);
+        segment
+            .iter()
+            .for_each(|split| chunk.add_split(split.clone(), false));
+        if chunk.exact_token_count() <= config.length_max {
+            return Ok(vec![chunk]);
+        }
+        let segment_config = Arc::new(config.with_initial_splits(segment));
+        let phase_start_time = std::time::Instant::now();
+        let chunks = if self.use_dfs_semantic_splitter {
+            match DfsTextChunker::run(&segment_config) {
+                // The linear chunker splits sentences larger than a chunk, which the DFS chunker can not.
+                Err(ChunkerError::ImpossibleForSeparator { .. }) => {
+                    LinearChunker::run(&segment_config)
+                }
+                result => result,
+            }
+        } else {
+            LinearChunker::run(&segment_config)
+        };
+        phase_durations.chunking += phase_start_time.elapsed();
+        chunks
+    }
+}
+
+/// The indices of the sentences that start a new segment.
+/// For each gap between sentences, the mean embeddings of up to `window` sentences on each side are compared, and a boundary is placed where their cosine distance is above the `percentile` of all gaps.
+fn semantic_breakpoints(embeddings: &[Vec<f32>], window: usize, percentile: f32) -> Vec<usize> {
+    if embeddings.len() < 2 {
+        return Vec::new();
+    }
+    let window = window.max(1);
+    let distances: Vec<f32> = (1..embeddings.len())
+        .map(|i| {
+            let before = mean_embedding(&embeddings[i.saturating_sub(window)..i]);
+            let after = mean_embedding(&embeddings[i..(i + window).min(embeddings.len())]);
+            1.0 - cosine_similarity(&before, &after)
+        })
+        .collect();
+    let mut sorted = distances.clone();
+    sorted.sort_by(f32::total_cmp);
+    let threshold_index = ((sorted.len() - 1) as f32 * percentile.clamp(0.0, 1.0)).round() as usize;
+    let threshold = sorted[threshold_index];
+    distances
+        .iter()
+        .enumerate()
+        .filter(|(_, &distance)| distance > threshold)
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+fn mean_embedding(embeddings: &[Vec<f32>]) -> Vec<f32> {
+    let mut mean = vec![0.0; embeddings[0].len()];
+    for embedding in embeddings {
+        mean.iter_mut().zip(embedding).for_each(|(sum, value)| *sum += value);
+    }
+    mean.iter_mut().for_each(|sum| *sum /= embeddings.len() as f32);
+    mean
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm_a = a.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|b| b * b).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embeds text by counting the words from each topic.
+    struct TopicEmbedder;
+
+    impl Embedder for TopicEmbedder {
+        fn embed(&self, texts: &[&str]) -> anyhow::Result<Vec<Vec<f32>>> {
+            const TOPICS: [&[&str]; 2] = [
+                &["cat", "cats", "kitten", "purr", "whiskers"],
+                &["rocket", "rockets", "launch", "orbit", "engine"],
+            ];
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    TOPICS
+                        .iter()
+                        .map(|words| {
+                            text.split(|c: char| !c.is_alphabetic())
+                                .filter(|word| words.contains(word))
+                                .count() as f32
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    const CATS: &str = "The cat sat in the sun and began to purr. Every cat in the house has long whiskers. A kitten chased the other cats around the room. The old cat licked its whiskers after dinner. Cats sleep for most of the day. The kitten would purr whenever it was held.";
+    const ROCKETS: &str = "The rocket stood on the pad before launch. Its engine was tested many times before the launch. The rocket reached orbit in under ten minutes. A second engine fired to raise the orbit. Reusable rockets land after each launch. The launch window opened at dawn for the rocket.";
+
+    #[test]
+    fn breakpoints() {
+        let embeddings: Vec<Vec<f32>> = [[1.0, 0.0]; 5]
+            .into_iter()
+            .chain([[0.0, 1.0]; 5])
+            .map(|embedding| embedding.to_vec())
+            .collect();
+        assert_eq!(semantic_breakpoints(&embeddings, 2, 0.9), vec![5]);
+        assert!(semantic_breakpoints(&[vec![1.0, 0.0]; 4], 2, 0.9).is_empty());
+    }
+
+    #[test]
+    fn semantic() {
+        let content = format!("{CATS} {ROCKETS}");
+        let chunks = TextChunker::new()
+            .unwrap()
+            .semantic(Arc::new(TopicEmbedder))
+            .run(&content)
+            .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].starts_with("The cat") && !chunks[0].contains("rocket"));
+        assert!(chunks[1].starts_with("The rocket") && !chunks[1].contains("cat"));
+
+        // Segments larger than a chunk are chunked, but chunks still never span the boundary.
+        let content = format!("{CATS} {CATS} {ROCKETS} {ROCKETS}");
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(64)
+            .semantic(Arc::new(TopicEmbedder))
+            .semantic_breakpoint_percentile(0.95)
+            .run_return_result(&content)
+            .unwrap();
+        assert!(res.token_counts().iter().all(|&x| x <= 64));
+        let chunks = res.chunks_to_text();
+        assert!(chunks.len() > 2);
+        assert!(chunks
+            .iter()
+            .all(|chunk| !(chunk.contains("cat") && chunk.contains("rocket"))));
+    }
+
+    #[test]
+    fn long_sentences() {
+        // Every sentence is larger than a chunk, so each is split further after segmentation.
+        let sentence = |word: &str| format!("The {word}{}.", format!(" and the {word}").repeat(20));
+        let content = format!(
+            "{} {} {}",
+            sentence("cat"),
+            sentence("kitten"),
+            sentence("rocket")
+        );
+        for overlap in [None, Some(OverlapStrategy::Tokens(4))] {
+            let mut chunker = TextChunker::new()
+                .unwrap()
+                .max_chunk_token_size(32)
+                .semantic(Arc::new(TopicEmbedder));
+            if let Some(overlap) = overlap {
+                chunker = chunker.overlap(overlap);
+            }
+            let mut res = chunker.run_return_result(&content).unwrap();
+            assert!(res.chunks.len() > 3);
+            assert!(res.token_counts().iter().all(|&x| x <= 32));
+        }
+    }
+
+    #[test]
+    fn single_sentence() {
+        let chunker = TextChunker::new()
+            .unwrap()
+            .semantic(Arc::new(TopicEmbedder));
+        let sentence = "The cat sat in the sun and began to purr.";
+        assert_eq!(chunker.run(sentence).unwrap(), vec![sentence.to_string()]);
+        assert!(TextChunker::new()
+            .unwrap()
+            .semantic(Arc::new(TopicEmbedder))
+            .code(CodeLanguage::Rust)
+            .run(sentence)
+            .is_err());
+    }
+}
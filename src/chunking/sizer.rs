@@ -0,0 +1,210 @@
+use crate::splitting::Separator;
+use llm_models::tokenizer::LlmTokenizer;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Measures the size of text, in whatever unit chunk limits are expressed in: tokens, characters, bytes, or words.
+/// The `max_chunk_token_size` and `min_chunk_token_size` of the [`super::TextChunker`] are in the sizer's units.
+pub trait ChunkSizer: Send + Sync {
+    /// The size of `text`.
+    fn size(&self, text: &str) -> u32;
+
+    /// Estimates the size a split adds to a chunk without measuring the joined text.
+    /// The chunkers sum these estimates to avoid measuring every candidate chunk, and measure the final chunks exactly.
+    ///
+    /// * `split_size` - The measured size of the split on its own.
+    /// * `separator` - The [`Separator`] the split was split on.
+    /// * `previous` - The [`Separator`] of the split it is joined after, or None if it is the first split in the chunk.
+    ///
+    /// The default is tuned for subword tokenizers, where joining splits with whitespace slightly reduces the total token count.
+    fn estimate_split_size(
+        &self,
+        split_size: u32,
+        separator: &Separator,
+        previous: Option<&Separator>,
+    ) -> f32 {
+        let split_size = match separator {
+            Separator::GraphemesUnicode => match previous {
+                None | Some(Separator::GraphemesUnicode) => 0.55,
+                _ => 1.0,
+            },
+            _ => split_size as f32,
+        };
+        if previous.is_none() {
+            return split_size;
+        }
+        let white_space_ratio = match separator {
+            Separator::None => {
+                unreachable!()
+            }
+            Separator::MarkdownHeading(_) | Separator::MarkdownBlock => 0.999,
+            Separator::Code { .. } | Separator::CodeLine => 0.999,
+            Separator::TwoPlusEoL => 0.999,
+            Separator::SingleEol | Separator::TableRow => 0.999,
+            Separator::SentencesRuleBased | Separator::Custom(_) => 0.998,
+            Separator::SentencesUnicode => 0.998,
+            Separator::WordsUnicode => 0.89,
+            Separator::GraphemesUnicode => 1.0,
+        };
+        split_size * white_space_ratio
+    }
+}
+
+impl ChunkSizer for LlmTokenizer {
+    fn size(&self, text: &str) -> u32 {
+        self.count_tokens(text)
+    }
+}
+
+/// Sizes text by Unicode scalar values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CharSizer;
+
+impl ChunkSizer for CharSizer {
+    fn size(&self, text: &str) -> u32 {
+        text.chars().count() as u32
+    }
+
+    fn estimate_split_size(
+        &self,
+        split_size: u32,
+        separator: &Separator,
+        previous: Option<&Separator>,
+    ) -> f32 {
+        split_size as f32 + joined_whitespace_len(separator, previous)
+    }
+}
+
+/// Sizes text by UTF-8 bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ByteSizer;
+
+impl ChunkSizer for ByteSizer {
+    fn size(&self, text: &str) -> u32 {
+        text.len() as u32
+    }
+
+    fn estimate_split_size(
+        &self,
+        split_size: u32,
+        separator: &Separator,
+        previous: Option<&Separator>,
+    ) -> f32 {
+        split_size as f32 + joined_whitespace_len(separator, previous)
+    }
+}
+
+/// Sizes text by Unicode words. Punctuation and whitespace are not counted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WordSizer;
+
+impl ChunkSizer for WordSizer {
+    fn size(&self, text: &str) -> u32 {
+        text.unicode_words().count() as u32
+    }
+
+    fn estimate_split_size(
+        &self,
+        split_size: u32,
+        _separator: &Separator,
+        _previous: Option<&Separator>,
+    ) -> f32 {
+        split_size as f32
+    }
+}
+
+/// Adapts a closure into a [`ChunkSizer`], for tokenizers that `llm_models` does not support.
+///
+/// ```ignore
+/// let sizer = FnSizer::new(|text: &str| my_tokenizer.encode(text).len() as u32);
+/// let chunker = TextChunker::new_with_sizer(Arc::new(sizer));
+/// ```
+pub struct FnSizer<F> {
+    size: F,
+}
+
+impl<F: Fn(&str) -> u32 + Send + Sync> FnSizer<F> {
+    pub fn new(size: F) -> Self {
+        Self { size }
+    }
+}
+
+impl<F: Fn(&str) -> u32 + Send + Sync> ChunkSizer for FnSizer<F> {
+    fn size(&self, text: &str) -> u32 {
+        (self.size)(text)
+    }
+}
+
+/// Sizes text against several [`ChunkSizer`]s with their own limits at once.
+/// Each size is scaled to the units of the first sizer, and the largest is returned, so text within the first sizer's limit is within every sizer's limit.
+pub(super) struct LimitedSizers {
+    /// Each sizer with its inclusive limit.
+    limits: Vec<(Arc<dyn ChunkSizer>, u32)>,
+}
+
+impl LimitedSizers {
+    pub(super) fn new(limits: Vec<(Arc<dyn ChunkSizer>, u32)>) -> Self {
+        Self { limits }
+    }
+}
+
+impl ChunkSizer for LimitedSizers {
+    fn size(&self, text: &str) -> u32 {
+        let scale = self.limits[0].1 as f64;
+        self.limits
+            .iter()
+            .map(|(sizer, limit)| {
+                (sizer.size(text) as f64 * scale / *limit as f64).ceil() as u32
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn estimate_split_size(
+        &self,
+        split_size: u32,
+        separator: &Separator,
+        previous: Option<&Separator>,
+    ) -> f32 {
+        let (sizer, _) = &self.limits[0];
+        sizer.estimate_split_size(split_size, separator, previous)
+    }
+}
+
+/// The whitespace usually added between two splits when they are joined, in characters or bytes.
+fn joined_whitespace_len(separator: &Separator, previous: Option<&Separator>) -> f32 {
+    if previous.is_none() {
+        return 0.0;
+    }
+    match separator {
+        Separator::TwoPlusEoL | Separator::MarkdownHeading(_) | Separator::MarkdownBlock => 2.0,
+        Separator::GraphemesUnicode => 0.0,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        let text = "Héllo, wörld! Two words.";
+        assert_eq!(CharSizer.size(text), 24);
+        assert_eq!(ByteSizer.size(text), 26);
+        assert_eq!(WordSizer.size(text), 4);
+        assert_eq!(FnSizer::new(|text: &str| text.split(',').count() as u32).size(text), 2);
+    }
+
+    #[test]
+    fn limited_sizers() {
+        let sizer = LimitedSizers::new(vec![
+            (Arc::new(CharSizer) as Arc<dyn ChunkSizer>, 100),
+            (Arc::new(WordSizer) as Arc<dyn ChunkSizer>, 10),
+        ]);
+        // 20 characters is 20% of the character limit, and 5 words is 50% of the word limit.
+        assert_eq!(sizer.size("one two three four 5"), 50);
+        assert!(sizer.size(&"word ".repeat(11)) > 100);
+        assert!(sizer.size(&"word ".repeat(10)) <= 100);
+    }
+}
@@ -0,0 +1,183 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+
+/// A chunk smaller than this fraction of the mean chunk token size is an orphan.
+const ORPHAN_CHUNK_RATIO: f32 = 0.5;
+
+/// Summary statistics of a set of sizes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeStats {
+    pub min: u32,
+    pub max: u32,
+    pub mean: f32,
+    /// The population standard deviation.
+    pub stddev: f32,
+    pub total: u64,
+}
+
+impl SizeStats {
+    /// The statistics of `sizes`. All zero if `sizes` is empty.
+    pub fn new(sizes: &[u32]) -> Self {
+        if sizes.is_empty() {
+            return Self::default();
+        }
+        let total: u64 = sizes.iter().map(|size| *size as u64).sum();
+        let mean = total as f32 / sizes.len() as f32;
+        let variance = sizes
+            .iter()
+            .map(|size| (*size as f32 - mean).powi(2))
+            .sum::<f32>()
+            / sizes.len() as f32;
+        Self {
+            min: *sizes.iter().min().unwrap(),
+            max: *sizes.iter().max().unwrap(),
+            mean,
+            stddev: variance.sqrt(),
+            total,
+        }
+    }
+}
+
+/// Chunk size and balance statistics of a [`ChunkerResult`], for comparing chunker configurations. Created by [`ChunkerResult::stats`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChunkStats {
+    pub chunk_count: usize,
+    /// The size of each chunk, in order, measured by the [`TextChunker`]'s own sizer.
+    pub chunk_token_sizes: Vec<u32>,
+    pub token_sizes: SizeStats,
+    /// The size of each chunk, in order, in Unicode scalar values.
+    pub chunk_char_sizes: Vec<u32>,
+    pub char_sizes: SizeStats,
+    pub incoming_text_token_count: u32,
+    pub incoming_text_char_count: u32,
+    /// The indices of chunks smaller than half the mean chunk token size, such as a short final chunk. Empty for a single chunk.
+    pub orphan_chunks: Vec<usize>,
+    /// The fraction of each chunk's source that is overlap from its neighbors, in bytes of the incoming text.
+    pub overlap_ratios: Vec<f32>,
+    /// The mean of `overlap_ratios`. 0.0 without overlap.
+    pub mean_overlap_ratio: f32,
+    /// How evenly sized the chunks are, from 0.0 to 1.0: one minus the coefficient of variation of the chunk token sizes. 1.0 if every chunk is the same size.
+    pub balance_score: f32,
+}
+
+impl ChunkStats {
+    /// Whether any chunk is an orphan. See [`ChunkStats::orphan_chunks`].
+    pub fn has_orphans(&self) -> bool {
+        !self.orphan_chunks.is_empty()
+    }
+}
+
+impl ChunkerResult {
+    /// Chunk size and balance statistics. Sizes are of the chunks' final text, including any overlap and prepended heading paths.
+    pub fn stats(&self) -> ChunkStats {
+        let texts: Vec<String> = self.chunks.iter().map(Chunk::joined_text).collect();
+        let chunk_token_sizes: Vec<u32> = texts.iter().map(|text| self.sizers[0].size(text)).collect();
+        let chunk_char_sizes: Vec<u32> = texts.iter().map(|text| text.chars().count() as u32).collect();
+        let token_sizes = SizeStats::new(&chunk_token_sizes);
+        let char_sizes = SizeStats::new(&chunk_char_sizes);
+
+        let orphan_chunks = if chunk_token_sizes.len() > 1 {
+            chunk_token_sizes
+                .iter()
+                .enumerate()
+                .filter(|(_, size)| (**size as f32) < token_sizes.mean * ORPHAN_CHUNK_RATIO)
+                .map(|(i, _)| i)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let overlap_ratios: Vec<f32> = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                let source_len = chunk.source_range().len();
+                let core_len = chunk.core_source_range().len();
+                if source_len == 0 {
+                    0.0
+                } else {
+                    source_len.saturating_sub(core_len) as f32 / source_len as f32
+                }
+            })
+            .collect();
+        let mean_overlap_ratio = if overlap_ratios.is_empty() {
+            0.0
+        } else {
+            overlap_ratios.iter().sum::<f32>() / overlap_ratios.len() as f32
+        };
+        let balance_score = if token_sizes.mean > 0.0 {
+            (1.0 - token_sizes.stddev / token_sizes.mean).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        ChunkStats {
+            chunk_count: self.chunks.len(),
+            chunk_token_sizes,
+            token_sizes,
+            chunk_char_sizes,
+            char_sizes,
+            incoming_text_token_count: self.sizers[0].size(&self.incoming_text),
+            incoming_text_char_count: self.incoming_text.chars().count() as u32,
+            orphan_chunks,
+            overlap_ratios,
+            mean_overlap_ratio,
+            balance_score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn size_stats() {
+        let stats = SizeStats::new(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!(stats.min, 2);
+        assert_eq!(stats.max, 9);
+        assert_eq!(stats.mean, 5.0);
+        assert_eq!(stats.stddev, 2.0);
+        assert_eq!(stats.total, 40);
+        assert_eq!(SizeStats::new(&[]), SizeStats::default());
+    }
+
+    #[test]
+    fn stats() {
+        let content = &CHUNK_TESTS.chunking_small.content;
+        let mut res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .run_return_result(content)
+            .unwrap();
+        let stats = res.stats();
+        assert_eq!(stats.chunk_count, res.chunks.len());
+        assert_eq!(stats.chunk_token_sizes, res.token_counts());
+        assert!(stats.token_sizes.max <= 128);
+        assert!(stats.token_sizes.min as f32 <= stats.token_sizes.mean);
+        assert!(stats.token_sizes.mean <= stats.token_sizes.max as f32);
+        assert!((0.0..=1.0).contains(&stats.balance_score));
+        assert_eq!(stats.mean_overlap_ratio, 0.0);
+        for i in &stats.orphan_chunks {
+            assert!((stats.chunk_token_sizes[*i] as f32) < stats.token_sizes.mean * ORPHAN_CHUNK_RATIO);
+        }
+
+        let res = TextChunker::new()
+            .unwrap()
+            .max_chunk_token_size(128)
+            .overlap(OverlapStrategy::Tokens(16))
+            .run_return_result(content)
+            .unwrap();
+        let stats = res.stats();
+        assert!(stats.mean_overlap_ratio > 0.0);
+        assert!(stats.overlap_ratios.iter().all(|ratio| (0.0..1.0).contains(ratio)));
+        // A single chunk is perfectly balanced, and never an orphan.
+        let stats = TextChunker::new()
+            .unwrap()
+            .run_return_result("A short text.")
+            .unwrap()
+            .stats();
+        assert_eq!(stats.balance_score, 1.0);
+        assert!(!stats.has_orphans());
+    }
+}
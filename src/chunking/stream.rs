@@ -0,0 +1,254 @@
+use super::*;
+use std::io::Read;
+
+/// The number of chunks worth of text buffered in each window.
+const STREAM_WINDOW_CHUNKS_DEFAULT: usize = 8;
+/// A conservative estimate of bytes per token, used to size the window without tokenizing the whole buffer.
+const STREAM_BYTES_PER_TOKEN_ESTIMATE: usize = 4;
+/// The most a window grows, as a multiple of its configured size, while it still fits in a single chunk.
+const STREAM_WINDOW_GROWTH_MAX: usize = 16;
+/// The size of each read from an [`std::io::Read`] source.
+const STREAM_READ_SIZE: usize = 64 * 1024;
+
+/// Chunks a stream of text incrementally with bounded memory. Created by [`TextChunker::run_stream`] and [`TextChunker::run_stream_reader`].
+///
+/// Text is buffered into a window of several chunks worth of tokens, and the window is chunked with the [`TextChunker`].
+/// Every chunk but the last is yielded, and the text of the last chunk is carried into the next window so the chunks stay balanced across window boundaries.
+/// Only the final window is chunked without a carried chunk. Overlap is applied within each window, but not backwards across window boundaries.
+/// A window that fits in a single chunk is doubled, up to [`STREAM_WINDOW_GROWTH_MAX`] times its size.
+pub struct ChunkStream<'a> {
+    chunker: &'a TextChunker,
+    pieces: Box<dyn Iterator<Item = std::io::Result<String>> + 'a>,
+    buffer: String,
+    pending: VecDeque<String>,
+    /// The configured window size.
+    base_window_bytes: usize,
+    /// The current window size, doubled while a window fits in a single chunk.
+    window_bytes: usize,
+    finished: bool,
+}
+
+impl<'a> ChunkStream<'a> {
+    pub(super) fn new(
+        chunker: &'a TextChunker,
+        pieces: impl Iterator<Item = std::io::Result<String>> + 'a,
+    ) -> Self {
+        let window_bytes = chunker.absolute_length_max as usize
+            * STREAM_WINDOW_CHUNKS_DEFAULT
+            * STREAM_BYTES_PER_TOKEN_ESTIMATE;
+        Self {
+            chunker,
+            pieces: Box::new(pieces),
+            buffer: String::new(),
+            pending: VecDeque::new(),
+            base_window_bytes: window_bytes,
+            window_bytes,
+            finished: false,
+        }
+    }
+
+    /// Sets how many chunks worth of text are buffered in each window. Default is 8.
+    /// Larger windows balance chunk sizes over more text at the cost of memory.
+    ///
+    /// * `window_chunks` - The number of chunks per window. Minimum is 2.
+    pub fn window_chunks(mut self, window_chunks: usize) -> Self {
+        self.base_window_bytes = self.chunker.absolute_length_max as usize
+            * window_chunks.max(2)
+            * STREAM_BYTES_PER_TOKEN_ESTIMATE;
+        self.window_bytes = self.base_window_bytes;
+        self
+    }
+
+    /// Reads pieces until the buffer holds a full window, or the stream ends.
+    fn fill_buffer(&mut self) -> Result<(), ChunkerError> {
+        while !self.finished && self.buffer.len() < self.window_bytes {
+            match self.pieces.next() {
+                Some(piece) => self.buffer.push_str(&piece?),
+                None => self.finished = true,
+            }
+        }
+        Ok(())
+    }
+
+    /// Chunks the current window and queues every chunk that is final.
+    fn chunk_window(&mut self) -> Result<(), ChunkerError> {
+        self.fill_buffer()?;
+        if self.buffer.trim().is_empty() {
+            self.buffer.clear();
+            return Ok(());
+        }
+        let mut result = self.chunker.text_chunker(&self.buffer)?;
+        if self.finished {
+            self.pending.extend(result.chunks_to_text());
+            self.buffer.clear();
+            return Ok(());
+        }
+        if result.chunks.len() < 2 {
+            let window_bytes_max = self.base_window_bytes * STREAM_WINDOW_GROWTH_MAX;
+            if self.window_bytes < window_bytes_max {
+                // The window fit in a single chunk, so there is nothing to balance against yet. Grow the window and read more.
+                self.window_bytes = (self.window_bytes * 2).min(window_bytes_max);
+                return Ok(());
+            }
+            // The window can not grow further, so its chunk is final.
+            self.pending.extend(result.chunks_to_text());
+            self.buffer.clear();
+            self.window_bytes = self.base_window_bytes;
+            return Ok(());
+        }
+        self.window_bytes = self.base_window_bytes;
+        let carry_start = result.chunks.last().unwrap().core_source_range().start;
+        let mut chunks = result.chunks_to_text();
+        chunks.pop();
+        self.pending.extend(chunks);
+        self.buffer.drain(..carry_start);
+        Ok(())
+    }
+}
+
+impl Iterator for ChunkStream<'_> {
+    type Item = Result<String, ChunkerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = self.pending.pop_front() {
+                return Some(Ok(chunk));
+            }
+            if self.finished && self.buffer.is_empty() {
+                return None;
+            }
+            if let Err(e) = self.chunk_window() {
+                // Stop the stream after the first error.
+                self.finished = true;
+                self.buffer.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Reads an [`std::io::Read`] source as UTF-8 pieces of text. A multi-byte character split across reads is held until the next read.
+pub(super) struct ReadPieces<R: Read> {
+    reader: R,
+    incomplete: Vec<u8>,
+}
+
+impl<R: Read> ReadPieces<R> {
+    pub(super) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            incomplete: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Iterator for ReadPieces<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = std::mem::take(&mut self.incomplete);
+        let read_start = bytes.len();
+        bytes.resize(read_start + STREAM_READ_SIZE, 0);
+        let read = loop {
+            match self.reader.read(&mut bytes[read_start..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        bytes.truncate(read_start + read);
+        if bytes.is_empty() {
+            return None;
+        }
+        let valid_up_to = match std::str::from_utf8(&bytes) {
+            Ok(_) => bytes.len(),
+            Err(e) if e.error_len().is_none() && read > 0 => e.valid_up_to(),
+            Err(e) => return Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e))),
+        };
+        self.incomplete = bytes.split_off(valid_up_to);
+        Some(Ok(String::from_utf8(bytes).expect("validated as UTF-8")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    fn non_whitespace(text: &str) -> String {
+        text.chars().filter(|c| !c.is_whitespace()).collect()
+    }
+
+    #[test]
+    fn stream() {
+        let content = &TEXT.really_long.content;
+        let pieces: Vec<String> = content
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks(1000)
+            .map(|piece| piece.iter().collect())
+            .collect();
+        let chunker = TextChunker::new().unwrap().max_chunk_token_size(256);
+        let chunks: Vec<String> = chunker
+            .run_stream(pieces)
+            .collect::<Result<Vec<String>, ChunkerError>>()
+            .unwrap();
+        assert!(chunks.len() > STREAM_WINDOW_CHUNKS_DEFAULT);
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunker.sizer.size(chunk) <= 256));
+        // Nothing is lost or repeated between windows.
+        assert_eq!(non_whitespace(&chunks.concat()), non_whitespace(content));
+    }
+
+    #[test]
+    fn stream_reader() {
+        let content = &TEXT.long.content;
+        let chunker = TextChunker::new().unwrap().max_chunk_token_size(256);
+        let chunks: Vec<String> = chunker
+            .run_stream_reader(std::io::Cursor::new(content.as_bytes()))
+            .window_chunks(2)
+            .collect::<Result<Vec<String>, ChunkerError>>()
+            .unwrap();
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunker.sizer.size(chunk) <= 256));
+        assert_eq!(non_whitespace(&chunks.concat()), non_whitespace(content));
+    }
+
+    /// Sizes every text as one unit, so any window fits in a single chunk.
+    struct WholeTextSizer;
+
+    impl ChunkSizer for WholeTextSizer {
+        fn size(&self, _text: &str) -> u32 {
+            1
+        }
+    }
+
+    #[test]
+    fn stream_window_growth() {
+        // The window stays a single chunk until it can not grow further, and its chunk is then yielded.
+        let content = "One. ".repeat(10_000);
+        let chunker =
+            TextChunker::new_with_sizer(Arc::new(WholeTextSizer)).max_chunk_token_size(64);
+        let mut stream = chunker.run_stream(content.split_inclusive(' ').map(str::to_string));
+        let base_window_bytes = stream.base_window_bytes;
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next() {
+            chunks.push(chunk.unwrap());
+            assert!(stream.window_bytes <= base_window_bytes * STREAM_WINDOW_GROWTH_MAX);
+        }
+        assert!(chunks.len() > 1);
+        assert_eq!(non_whitespace(&chunks.concat()), non_whitespace(&content));
+    }
+
+    #[test]
+    fn read_pieces_utf8() {
+        let content = "é".repeat(STREAM_READ_SIZE);
+        let pieces: Vec<String> = ReadPieces::new(std::io::Cursor::new(content.as_bytes()))
+            .collect::<std::io::Result<Vec<String>>>()
+            .unwrap();
+        assert!(pieces.len() > 1);
+        assert_eq!(pieces.concat(), content);
+    }
+}
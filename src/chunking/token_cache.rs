@@ -0,0 +1,137 @@
+use super::*;
+use std::{collections::HashMap, sync::Mutex};
+
+/// The number of bytes on each side of a junction between two splits that are tokenized to measure how joining the splits changes their token count.
+/// Tokens rarely span more than a few bytes, so the change is measured exactly in practice.
+const JUNCTION_WINDOW_BYTES: usize = 32;
+
+/// Caches the token counts of a [`ChunkerConfig`]'s splits by their range in the base text, and how joining two adjacent splits changes their combined count.
+/// With both cached, the token count of a chunk is updated incrementally as splits are added and removed, instead of tokenizing the whole chunk each time.
+#[derive(Default)]
+pub(super) struct TokenCache {
+    splits: Mutex<HashMap<Range<usize>, u32>>,
+    junctions: Mutex<HashMap<(Range<usize>, Range<usize>), i64>>,
+}
+
+impl TokenCache {
+    /// The token count of a split, tokenized once per range.
+    pub(super) fn split_token_count(&self, tokenizer: &TokenCounter, split: &TextSplit) -> u32 {
+        if let Some(token_count) = self.splits.lock().unwrap().get(&split.indices) {
+            return *token_count;
+        }
+        let token_count = tokenizer.count_tokens(split.text());
+        self.splits
+            .lock()
+            .unwrap()
+            .insert(split.indices.clone(), token_count);
+        token_count
+    }
+
+    /// How many tokens joining `left` and the following `right` with `joiner` between them adds to the sum of their token counts. Usually 0 or slightly negative.
+    /// Measured by tokenizing a bounded window on each side of the junction, with and without the joiner.
+    pub(super) fn junction_token_count(
+        &self,
+        tokenizer: &TokenCounter,
+        left: &TextSplit,
+        right: &TextSplit,
+        joiner: &str,
+    ) -> i64 {
+        let key = (left.indices.clone(), right.indices.clone());
+        if let Some(token_count) = self.junctions.lock().unwrap().get(&key) {
+            return *token_count;
+        }
+        let left_window = left_window(left.text());
+        let right_window = right_window(right.text());
+        let joined = format!("{left_window}{joiner}{right_window}");
+        let token_count = tokenizer.count_boundary_tokens(&joined) as i64
+            - tokenizer.count_boundary_tokens(left_window) as i64
+            - tokenizer.count_boundary_tokens(right_window) as i64;
+        self.junctions.lock().unwrap().insert(key, token_count);
+        token_count
+    }
+}
+
+/// The end of `text` within [`JUNCTION_WINDOW_BYTES`], starting at whitespace where possible so it starts on a token boundary.
+fn left_window(text: &str) -> &str {
+    if text.len() <= JUNCTION_WINDOW_BYTES {
+        return text;
+    }
+    let mut start = text.len() - JUNCTION_WINDOW_BYTES;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let window = &text[start..];
+    match window.find(char::is_whitespace) {
+        Some(whitespace) => &window[whitespace..],
+        None => window,
+    }
+}
+
+/// The start of `text` within [`JUNCTION_WINDOW_BYTES`], ending before whitespace where possible so it ends on a token boundary.
+fn right_window(text: &str) -> &str {
+    if text.len() <= JUNCTION_WINDOW_BYTES {
+        return text;
+    }
+    let mut end = JUNCTION_WINDOW_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let window = &text[..end];
+    match window.rfind(char::is_whitespace) {
+        Some(whitespace) if whitespace > 0 => &window[..whitespace],
+        _ => window,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_text::*;
+
+    #[test]
+    fn windows() {
+        let text = "The quick brown fox jumps over the lazy dog again and again.";
+        assert_eq!(left_window(text), " the lazy dog again and again.");
+        assert_eq!(right_window(text), "The quick brown fox jumps over");
+        assert_eq!(left_window("short"), "short");
+        assert_eq!(right_window("short"), "short");
+        // Windows never split a character.
+        let text = "é".repeat(40);
+        assert!(left_window(&text).len() <= JUNCTION_WINDOW_BYTES);
+        assert!(right_window(&text).len() <= JUNCTION_WINDOW_BYTES);
+    }
+
+    #[test]
+    fn incremental_token_count() {
+        let tokenizer = TokenCounter::new(Arc::new(
+            LlmTokenizer::new_tiktoken(TOKENIZER_TIKTOKEN_DEFAULT).unwrap(),
+        ));
+        let config = Arc::new(
+            ChunkerConfig::new(
+                &ChunksFound::new(true),
+                Separator::SentencesRuleBased,
+                &CHUNK_TESTS.chunking_small.content,
+                64,
+                None,
+                None,
+                TextMode::Cleaned,
+                None,
+                None,
+                tokenizer.clone(),
+            )
+            .unwrap(),
+        );
+        let mut chunk = Chunk::new(&config);
+        for split in config.initial_splits.iter().take(12) {
+            chunk.add_split(split.clone(), false);
+        }
+        chunk.remove_split(false);
+        chunk.add_split(config.initial_splits[11].clone(), false);
+        let calls = tokenizer.calls();
+        let incremental = chunk.token_count(false);
+        // The incremental count did not tokenize the chunk, and matches the tokenizer's count of the joined text.
+        assert_eq!(tokenizer.calls(), calls);
+        assert!((incremental - chunk.exact_token_count()).abs() <= 1.0);
+        assert_eq!(tokenizer.calls(), calls + 1);
+    }
+}
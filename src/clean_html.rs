@@ -1,19 +1,37 @@
 use anyhow::Result;
 use html2text::{config, render::text_renderer::TrivialDecorator};
 use readability::extractor;
-use std::io::Cursor;
+use regex::Regex;
+use std::{io::Cursor, sync::LazyLock};
 use url::Url;
 
+static HTML_HEADING_OPEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<h([1-6])(?:\s[^>]*)?>").unwrap());
+
 pub fn clean_html(html: &str) -> Result<String> {
+    clean_html_inner(html, false)
+}
+
+/// Like [`clean_html`], but prefixes `h1`–`h6` headings with Markdown heading markers, such as `## Installation`, so the chunker can detect them and split on them.
+pub fn clean_html_with_heading_markers(html: &str) -> Result<String> {
+    clean_html_inner(html, true)
+}
+
+fn clean_html_inner(html: &str, heading_markers: bool) -> Result<String> {
     let mut input = Cursor::new(html);
 
     let readable = extractor::extract(&mut input, &Url::parse("http://example.com").unwrap())?;
+    let content = if heading_markers {
+        mark_headings(&readable.content)
+    } else {
+        readable.content
+    };
     // Convert html to text with html2text
     // Trivial decorator removes all tags and leaves only text
     let decorator = TrivialDecorator::new();
     let text = config::with_decorator(decorator)
         .allow_width_overflow()
-        .string_from_read(readable.content.as_bytes(), 10000)
+        .string_from_read(content.as_bytes(), 10000)
         .unwrap();
 
     // Finally, remove excess whitespace
@@ -22,6 +40,16 @@ pub fn clean_html(html: &str) -> Result<String> {
         .run(&text))
 }
 
+/// Prefixes the text of `h1`–`h6` elements with Markdown heading markers, so headings survive conversion to text and can be chunked on.
+fn mark_headings(html: &str) -> String {
+    HTML_HEADING_OPEN
+        .replace_all(html, |captures: &regex::Captures| {
+            let level: usize = captures[1].parse().unwrap();
+            format!("{}{} ", &captures[0], "#".repeat(level))
+        })
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,5 +62,14 @@ mod tests {
         assert!(
             !cleaned.contains("<p>") && !cleaned.contains("<div>") && !cleaned.contains("<h1>")
         );
+        assert!(!cleaned.lines().any(|line| line.starts_with('#')));
+    }
+
+    #[test]
+    fn test_mark_headings() {
+        assert_eq!(
+            mark_headings("<h1>Title</h1><p>Text</p><H3 class=\"x\">Sub</H3><hr>"),
+            "<h1># Title</h1><p>Text</p><H3 class=\"x\">### Sub</H3><hr>"
+        );
     }
 }
@@ -1,5 +1,5 @@
 use regex::Regex;
-use std::sync::LazyLock;
+use std::{ops::Range, sync::LazyLock};
 
 #[derive(Default)]
 pub enum Newlines {
@@ -14,6 +14,7 @@ pub struct TextCleaner {
     pub newlines: Newlines,
     pub remove_non_basic_ascii: bool,
     pub remve_citations: bool,
+    pub keep_whitespace: bool,
 }
 impl TextCleaner {
     pub fn new() -> Self {
@@ -50,34 +51,237 @@ impl TextCleaner {
         self
     }
 
+    /// Only normalizes carriage returns to `\n`, and leaves spaces, tabs, and escaped sequences untouched.
+    /// For structured text like Markdown where indentation is meaningful.
+    pub fn keep_whitespace(mut self) -> Self {
+        self.keep_whitespace = true;
+        self
+    }
+
     pub fn run(&self, text: &str) -> String {
-        let text = END_OF_LINE_REGEX.replace_all(text, "\n");
-        let text = END_OF_PARAGRAPH_REGEX.replace_all(&text, "\n\n");
-        let text = WHITE_SPACE_REGEX.replace_all(&text, " ");
-
-        let text = match self.newlines {
-            Newlines::Space => SINGLE_NEWLINE_REGEX.replace_all(&text, " "),
-            Newlines::Single => SINGLE_NEWLINE_REGEX.replace_all(&text, "\n"),
-            Newlines::TwoPlus => TWO_PLUS_NEWLINE_REGEX.replace_all(&text, "\n\n"),
-            Newlines::None => text,
-        };
+        let mut text = text.to_string();
+        for (regex, replacement) in self.replacements() {
+            text = regex.replace_all(&text, replacement).into_owned();
+        }
+        text.trim().to_string()
+    }
+
+    /// Cleans the text the same way as [`TextCleaner::run`], and also returns an [`OffsetMap`] that maps byte offsets in the cleaned text back to byte offsets in the incoming text.
+    pub fn run_with_offset_map(&self, text: &str) -> (String, OffsetMap) {
+        let mut text = text.to_string();
+        let mut offset_map = OffsetMap::identity(text.len());
+        for (regex, replacement) in self.replacements() {
+            (text, offset_map) = offset_map.replace_all(regex, &text, replacement);
+        }
+        let trimmed_start = text.len() - text.trim_start().len();
+        let trimmed = text.trim();
+        let offset_map = offset_map.slice(trimmed_start..trimmed_start + trimmed.len());
+        (trimmed.to_string(), offset_map)
+    }
 
-        let text = if self.remove_non_basic_ascii {
-            UNWANTED_CHARS_REGEX.replace_all(&text, "")
+    /// The ordered regex replacements applied by the cleaner. Shared by [`TextCleaner::run`] and [`TextCleaner::run_with_offset_map`] so they can never disagree.
+    fn replacements(&self) -> Vec<(&'static Regex, &'static str)> {
+        let mut replacements: Vec<(&'static Regex, &'static str)> = if self.keep_whitespace {
+            vec![(&*CARRIAGE_RETURN_REGEX, "\n")]
         } else {
-            text
+            vec![
+                (&*END_OF_LINE_REGEX, "\n"),
+                (&*END_OF_PARAGRAPH_REGEX, "\n\n"),
+                (&*WHITE_SPACE_REGEX, " "),
+            ]
         };
+        match self.newlines {
+            Newlines::Space => replacements.push((&*SINGLE_NEWLINE_REGEX, " ")),
+            Newlines::Single => replacements.push((&*SINGLE_NEWLINE_REGEX, "\n")),
+            Newlines::TwoPlus => replacements.push((&*TWO_PLUS_NEWLINE_REGEX, "\n\n")),
+            Newlines::None => (),
+        }
+        if self.remove_non_basic_ascii {
+            replacements.push((&*UNWANTED_CHARS_REGEX, ""));
+        }
+        if self.remve_citations {
+            replacements.push((&*CITATIONS_REGEX, ""));
+        }
+        if !self.keep_whitespace {
+            replacements.push((&*SINGLE_SPACE_REGEX, " "));
+        }
+        replacements
+    }
+}
+
+/// A contiguous run of cleaned text and the source bytes it was produced from.
+#[derive(Debug, Clone, PartialEq)]
+struct OffsetSegment {
+    cleaned: Range<usize>,
+    source: Range<usize>,
+    /// True if the cleaned bytes are a verbatim copy of the source bytes, so offsets can be mapped one to one.
+    /// Otherwise the whole cleaned range maps to the whole source range.
+    linear: bool,
+}
+
+/// Maps byte offsets in cleaned text back to byte offsets in the text it was cleaned from.
+/// Built by [`TextCleaner::run_with_offset_map`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OffsetMap {
+    segments: Vec<OffsetSegment>,
+    cleaned_len: usize,
+    source_len: usize,
+}
+
+impl OffsetMap {
+    /// A map for text that was not modified.
+    pub fn identity(len: usize) -> Self {
+        let mut offset_map = Self::empty(len);
+        offset_map.push_copy(0..len);
+        offset_map
+    }
+
+    fn empty(source_len: usize) -> Self {
+        Self {
+            segments: Vec::new(),
+            cleaned_len: 0,
+            source_len,
+        }
+    }
+
+    /// Maps a byte range of the cleaned text to the byte range of the source text it was produced from.
+    /// Ranges that start or end inside a replaced sequence are widened to cover the whole sequence.
+    pub fn source_range(&self, cleaned: &Range<usize>) -> Range<usize> {
+        let start = self.source_start(cleaned.start);
+        if cleaned.end <= cleaned.start {
+            return start..start;
+        }
+        start..self.source_end(cleaned.end).max(start)
+    }
+
+    /// The length of the source text in bytes.
+    pub fn source_len(&self) -> usize {
+        self.source_len
+    }
+
+    /// The length of the cleaned text in bytes.
+    pub fn cleaned_len(&self) -> usize {
+        self.cleaned_len
+    }
 
-        let text = if self.remve_citations {
-            CITATIONS_REGEX.replace_all(&text, "")
+    fn source_start(&self, cleaned: usize) -> usize {
+        match self.segment_index(cleaned) {
+            Some(index) => {
+                let segment = &self.segments[index];
+                if segment.linear {
+                    segment.source.start + (cleaned - segment.cleaned.start)
+                } else {
+                    segment.source.start
+                }
+            }
+            None => self.segments.last().map_or(0, |segment| segment.source.end),
+        }
+    }
+
+    fn source_end(&self, cleaned: usize) -> usize {
+        match self.segment_index(cleaned - 1) {
+            Some(index) => {
+                let segment = &self.segments[index];
+                if segment.linear {
+                    segment.source.start + (cleaned - segment.cleaned.start)
+                } else {
+                    segment.source.end
+                }
+            }
+            None => self.segments.last().map_or(0, |segment| segment.source.end),
+        }
+    }
+
+    fn segment_index(&self, cleaned: usize) -> Option<usize> {
+        let index = self
+            .segments
+            .partition_point(|segment| segment.cleaned.end <= cleaned);
+        if index < self.segments.len() && self.segments[index].cleaned.start <= cleaned {
+            Some(index)
         } else {
-            text
-        };
+            None
+        }
+    }
+
+    /// Appends cleaned bytes that are a verbatim copy of `source`.
+    fn push_copy(&mut self, source: Range<usize>) {
+        self.push_segment(source.len(), source, true);
+    }
+
+    /// Appends `len` cleaned bytes that replaced the `source` bytes.
+    fn push_replaced(&mut self, len: usize, source: Range<usize>) {
+        self.push_segment(len, source, false);
+    }
+
+    fn push_segment(&mut self, len: usize, source: Range<usize>, linear: bool) {
+        if len == 0 {
+            return;
+        }
+        let cleaned = self.cleaned_len..self.cleaned_len + len;
+        self.cleaned_len += len;
+        if let Some(last) = self.segments.last_mut() {
+            if linear && last.linear && last.source.end == source.start {
+                last.cleaned.end = cleaned.end;
+                last.source.end = source.end;
+                return;
+            }
+        }
+        self.segments.push(OffsetSegment {
+            cleaned,
+            source,
+            linear,
+        });
+    }
 
-        SINGLE_SPACE_REGEX
-            .replace_all(&text, " ")
-            .trim()
-            .to_string()
+    /// Appends a verbatim copy of the `cleaned` range of the text this map describes.
+    fn push_copied(&mut self, from: &OffsetMap, cleaned: Range<usize>) {
+        if cleaned.is_empty() {
+            return;
+        }
+        let first = from
+            .segment_index(cleaned.start)
+            .expect("cleaned range is within the offset map");
+        for segment in &from.segments[first..] {
+            if segment.cleaned.start >= cleaned.end {
+                break;
+            }
+            let start = segment.cleaned.start.max(cleaned.start);
+            let end = segment.cleaned.end.min(cleaned.end);
+            if segment.linear {
+                let source_start = segment.source.start + (start - segment.cleaned.start);
+                self.push_copy(source_start..source_start + (end - start));
+            } else {
+                self.push_replaced(end - start, segment.source.clone());
+            }
+        }
+    }
+
+    /// Runs `regex.replace_all` on text described by this map, returning the new text and its map back to the original source.
+    fn replace_all(&self, regex: &Regex, text: &str, replacement: &str) -> (String, OffsetMap) {
+        let mut new_text = String::with_capacity(text.len());
+        let mut new_map = OffsetMap::empty(self.source_len);
+        let mut last_end = 0;
+        for m in regex.find_iter(text) {
+            new_map.push_copied(self, last_end..m.start());
+            new_text.push_str(&text[last_end..m.start()]);
+            if m.as_str() == replacement {
+                new_map.push_copied(self, m.range());
+            } else {
+                new_map.push_replaced(replacement.len(), self.source_range(&m.range()));
+            }
+            new_text.push_str(replacement);
+            last_end = m.end();
+        }
+        new_map.push_copied(self, last_end..text.len());
+        new_text.push_str(&text[last_end..]);
+        (new_text, new_map)
+    }
+
+    /// A map for the `cleaned` sub range of the text this map describes.
+    fn slice(&self, cleaned: Range<usize>) -> OffsetMap {
+        let mut new_map = OffsetMap::empty(self.source_len);
+        new_map.push_copied(self, cleaned);
+        new_map
     }
 }
 
@@ -119,6 +323,7 @@ pub static END_OF_LINE_SEQUENCES: LazyLock<Vec<&'static str>> = LazyLock::new(||
 });
 pub static END_OF_LINE_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(&END_OF_LINE_SEQUENCES.join("|")).unwrap());
+pub static CARRIAGE_RETURN_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\r\n?").unwrap());
 pub static SINGLE_NEWLINE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n{1,}").unwrap());
 
 //
@@ -237,6 +442,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_offset_map() {
+        let ascii_text = "  Ascii\tspaces here.\r\n\r\nUnicode\u{00A0}spaces\n\n\n\nhere.  ";
+        let (cleaned, offset_map) = TextCleaner::new()
+            .reduce_newlines_to_double_newline()
+            .run_with_offset_map(ascii_text);
+        assert_eq!(
+            cleaned,
+            TextCleaner::new()
+                .reduce_newlines_to_double_newline()
+                .run(ascii_text)
+        );
+        assert_eq!(offset_map.cleaned_len(), cleaned.len());
+        assert_eq!(offset_map.source_len(), ascii_text.len());
+        for word in ["Ascii", "spaces here.", "Unicode", "here."] {
+            let start = cleaned.find(word).unwrap();
+            let source_range = offset_map.source_range(&(start..start + word.len()));
+            assert_eq!(&ascii_text[source_range], word);
+        }
+        let source_range = offset_map.source_range(&(0..cleaned.len()));
+        assert_eq!(&ascii_text[source_range], ascii_text.trim());
+        let start = cleaned.find("\n\nUnicode").unwrap();
+        let source_range = offset_map.source_range(&(start..start + 2));
+        assert_eq!(&ascii_text[source_range], "\r\n\r\n");
+    }
+
     #[test]
     fn test_strip_unwanted_chars() {
         let ascii_text = r#"This is a "test" sentence. It include's 'single' and "double" quotes, as well as other basic punctuation characters like commas, periods, question marks?, exclamation marks!, colons:, semicolons;, hyphens-, parentheses(), square brackets[], curly braces{}, and special characters $&@#%^*(). It also includes some advanced punctuation characters that should be removed, such as ¡¢£¤¥¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾¿ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞßàáâãäåæçèéêëìíîïðñòóôõö÷øùúûüýþÿ"#;
@@ -1,5 +1,5 @@
 use linkify::{LinkFinder, LinkKind};
-use std::{collections::HashSet, str::FromStr};
+use std::{collections::HashSet, ops::Range, str::FromStr};
 use url::Url;
 
 pub fn extract_urls<T: AsRef<str>>(input: T) -> Vec<Url> {
@@ -12,3 +12,21 @@ pub fn extract_urls<T: AsRef<str>>(input: T) -> Vec<Url> {
         .filter(|url| unique_urls.insert(url.clone()))
         .collect()
 }
+
+/// The byte ranges of the URLs found by [`extract_urls`], in order, including duplicates and URLs that fail to parse.
+pub fn extract_url_ranges<T: AsRef<str>>(input: T) -> Vec<Range<usize>> {
+    link_ranges(input.as_ref(), LinkKind::Url)
+}
+
+/// The byte ranges of the e-mail addresses in `input`, in order.
+pub fn extract_email_ranges<T: AsRef<str>>(input: T) -> Vec<Range<usize>> {
+    link_ranges(input.as_ref(), LinkKind::Email)
+}
+
+fn link_ranges(input: &str, kind: LinkKind) -> Vec<Range<usize>> {
+    LinkFinder::new()
+        .kinds(&[kind])
+        .links(input)
+        .map(|link| link.start()..link.end())
+        .collect()
+}
@@ -7,7 +7,7 @@ pub mod local_content;
 pub mod splitting;
 pub mod test_text;
 
-pub use chunking::TextChunker;
+pub use chunking::{ChunkerError, TextChunker};
 pub use clean_text::TextCleaner;
 pub use splitting::TextSplitter;
 
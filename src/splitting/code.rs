@@ -0,0 +1,362 @@
+use std::ops::Range;
+
+/// The deepest block nesting the code separators split on before falling back to single lines.
+pub const CODE_DEPTH_MAX: usize = 4;
+
+/// Programming languages understood by the code separators.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, serde::Serialize, serde::Deserialize)]
+pub enum CodeLanguage {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    Java,
+}
+
+impl CodeLanguage {
+    /// Guesses the language from a file extension such as `rs` or `py`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.trim_start_matches('.').to_lowercase().as_str() {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "tsx" | "mts" | "cts" => Some(Self::TypeScript),
+            "go" => Some(Self::Go),
+            "java" => Some(Self::Java),
+            _ => None,
+        }
+    }
+
+    /// Python blocks are defined by indentation. Every other supported language uses braces.
+    fn uses_indentation(&self) -> bool {
+        matches!(self, Self::Python)
+    }
+
+    fn line_comment(&self) -> &'static str {
+        match self {
+            Self::Python => "#",
+            _ => "//",
+        }
+    }
+
+    /// Lines that belong to the item that follows them: comments, attributes, and decorators.
+    fn attaches_to_next(&self, line: &str) -> bool {
+        if line.starts_with(self.line_comment()) {
+            return true;
+        }
+        match self {
+            Self::Rust => line.starts_with("/*") || line.starts_with("#["),
+            Self::Python => line.starts_with('@'),
+            Self::JavaScript | Self::TypeScript | Self::Java => {
+                line.starts_with("/*") || line.starts_with('@')
+            }
+            Self::Go => line.starts_with("/*"),
+        }
+    }
+
+    /// Lines that continue the previous statement, and so can never start a new item.
+    fn is_continuation(&self, line: &str) -> bool {
+        const CONTINUATION_TOKENS: [&str; 15] = [
+            "}", ")", "]", ".", "?", "&&", "||", "+", "=", "->", "else", "catch", "finally",
+            "elif", "except",
+        ];
+        CONTINUATION_TOKENS.iter().any(|token| {
+            line.starts_with(token)
+                && (!token.chars().all(char::is_alphabetic)
+                    || !line[token.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_'))
+        }) || (matches!(self, Self::Rust) && line.starts_with("where"))
+    }
+}
+
+/// Splits source code into the items found at block nesting `depth`, keeping each item's comments, attributes, and decorators with it.
+/// For brace languages `depth` is the bracket depth, and for Python it is the indentation level.
+/// Everything before the first item at `depth`, such as an enclosing `impl` or `class` line, is its own split.
+pub fn split_code_into_indices(
+    text: &str,
+    language: CodeLanguage,
+    depth: usize,
+) -> Vec<Range<usize>> {
+    let lines = analyze_lines(text, language);
+    let level_indent = if language.uses_indentation() {
+        let mut indents: Vec<usize> = lines
+            .iter()
+            .filter(|line| line.is_code_start() && line.depth_start == 0)
+            .map(|line| line.indent)
+            .collect();
+        indents.sort_unstable();
+        indents.dedup();
+        match indents.get(depth) {
+            Some(indent) => *indent,
+            None => return vec![0..text.len()],
+        }
+    } else {
+        0
+    };
+
+    let mut item_starts: Vec<usize> = vec![0];
+    let mut previous_attaches = false;
+    for line in &lines {
+        if line.blank {
+            continue;
+        }
+        let at_depth = if language.uses_indentation() {
+            line.depth_start == 0 && line.indent <= level_indent
+        } else {
+            line.depth_start <= depth
+        };
+        if line.is_code_start()
+            && at_depth
+            && !previous_attaches
+            && !line.continuation
+            && line.range.start != 0
+        {
+            item_starts.push(line.range.start);
+        }
+        // Lines inside a block comment keep it attached to the following item.
+        if !line.in_literal {
+            previous_attaches = line.attaches_to_next;
+        }
+    }
+    item_starts.push(text.len());
+    item_starts
+        .windows(2)
+        .map(|window| Range {
+            start: window[0],
+            end: window[1],
+        })
+        .collect()
+}
+
+/// Splits source code into its non-blank lines.
+pub fn split_code_lines_into_indices(text: &str) -> Vec<Range<usize>> {
+    let mut line_starts: Vec<usize> = vec![0];
+    line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+    line_starts.push(text.len());
+    line_starts
+        .windows(2)
+        .map(|window| Range {
+            start: window[0],
+            end: window[1],
+        })
+        .collect()
+}
+
+/// What the code separators need to know about each line.
+struct LineInfo {
+    range: Range<usize>,
+    blank: bool,
+    /// The line starts inside a string literal or block comment.
+    in_literal: bool,
+    /// Bracket depth at the start of the line.
+    depth_start: usize,
+    /// Indentation width, with tabs counted as four spaces.
+    indent: usize,
+    attaches_to_next: bool,
+    continuation: bool,
+}
+
+impl LineInfo {
+    fn is_code_start(&self) -> bool {
+        !self.blank && !self.in_literal
+    }
+}
+
+/// Lexer state carried across lines.
+#[derive(PartialEq, Clone, Copy)]
+enum LexState {
+    Code,
+    BlockComment(usize),
+    Str { delimiter: char, triple: bool },
+}
+
+fn analyze_lines(text: &str, language: CodeLanguage) -> Vec<LineInfo> {
+    let mut lines = Vec::new();
+    let mut state = LexState::Code;
+    let mut depth: usize = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let indent = line[..line.len() - trimmed.len()]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        let in_literal = state != LexState::Code;
+        lines.push(LineInfo {
+            range: offset..offset + line.len(),
+            blank: trimmed.is_empty(),
+            in_literal,
+            depth_start: depth,
+            indent,
+            attaches_to_next: !in_literal && language.attaches_to_next(trimmed),
+            continuation: !in_literal && language.is_continuation(trimmed),
+        });
+        (state, depth) = lex_line(line, language, state, depth);
+        offset += line.len();
+    }
+    lines
+}
+
+/// Lexes one line, skipping strings and comments, and returns the state and bracket depth at its end.
+fn lex_line(
+    line: &str,
+    language: CodeLanguage,
+    mut state: LexState,
+    mut depth: usize,
+) -> (LexState, usize) {
+    let chars: Vec<char> = line.chars().collect();
+    let line_comment: Vec<char> = language.line_comment().chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match state {
+            LexState::BlockComment(nesting) => {
+                if c == '*' && chars.get(i + 1) == Some(&'/') {
+                    state = if nesting > 1 {
+                        LexState::BlockComment(nesting - 1)
+                    } else {
+                        LexState::Code
+                    };
+                    i += 1;
+                } else if language == CodeLanguage::Rust
+                    && c == '/'
+                    && chars.get(i + 1) == Some(&'*')
+                {
+                    state = LexState::BlockComment(nesting + 1);
+                    i += 1;
+                }
+            }
+            LexState::Str { delimiter, triple } => {
+                if c == '\\' {
+                    i += 1;
+                } else if c == delimiter {
+                    if !triple {
+                        state = LexState::Code;
+                    } else if chars.get(i + 1) == Some(&delimiter)
+                        && chars.get(i + 2) == Some(&delimiter)
+                    {
+                        state = LexState::Code;
+                        i += 2;
+                    }
+                }
+            }
+            LexState::Code => {
+                if chars[i..].starts_with(&line_comment) {
+                    break;
+                } else if language != CodeLanguage::Python
+                    && c == '/'
+                    && chars.get(i + 1) == Some(&'*')
+                {
+                    state = LexState::BlockComment(1);
+                    i += 1;
+                } else if c == '"' || (c == '`' && language != CodeLanguage::Python) {
+                    state = LexState::Str {
+                        delimiter: c,
+                        triple: false,
+                    };
+                    if language == CodeLanguage::Python
+                        && chars.get(i + 1) == Some(&c)
+                        && chars.get(i + 2) == Some(&c)
+                    {
+                        state = LexState::Str {
+                            delimiter: c,
+                            triple: true,
+                        };
+                        i += 2;
+                    }
+                } else if c == '\'' {
+                    if language == CodeLanguage::Rust {
+                        // A char literal, otherwise a lifetime.
+                        if chars.get(i + 1) == Some(&'\\') {
+                            state = LexState::Str {
+                                delimiter: c,
+                                triple: false,
+                            };
+                        } else if chars.get(i + 2) == Some(&'\'') {
+                            i += 2;
+                        }
+                    } else {
+                        let triple = language == CodeLanguage::Python
+                            && chars.get(i + 1) == Some(&c)
+                            && chars.get(i + 2) == Some(&c);
+                        state = LexState::Str {
+                            delimiter: c,
+                            triple,
+                        };
+                        if triple {
+                            i += 2;
+                        }
+                    }
+                } else if matches!(c, '{' | '(' | '[') {
+                    depth += 1;
+                } else if matches!(c, '}' | ')' | ']') {
+                    depth = depth.saturating_sub(1);
+                }
+            }
+        }
+        i += 1;
+    }
+    // Strings other than multi-line ones can't span lines. Recover from unbalanced quotes, e.g. an apostrophe in a comment.
+    if let LexState::Str { delimiter, triple } = state {
+        let multi_line =
+            triple || delimiter == '`' || (language == CodeLanguage::Rust && delimiter == '"');
+        if !multi_line {
+            state = LexState::Code;
+        }
+    }
+    (state, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str, ranges: Vec<Range<usize>>) -> Vec<&str> {
+        ranges.into_iter().map(|range| text[range].trim()).collect()
+    }
+
+    const RUST: &str = "use std::fmt;\n\n/// Doc comment.\n#[derive(Debug)]\npub struct Foo {\n    a: u32,\n}\n\nimpl Foo {\n    /// Creates a Foo.\n    pub fn new(\n        a: u32,\n    ) -> Self {\n        let s = \"{ not a brace\";\n        Self { a }\n    }\n\n    fn get<'a>(&'a self) -> char {\n        '}'\n    }\n}\n";
+
+    #[test]
+    fn rust() {
+        let items = texts(RUST, split_code_into_indices(RUST, CodeLanguage::Rust, 0));
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], "use std::fmt;");
+        assert!(items[1].starts_with("/// Doc comment.\n#[derive(Debug)]\npub struct Foo {"));
+        assert!(items[2].starts_with("impl Foo {") && items[2].ends_with('}'));
+
+        let impl_block = &RUST[RUST.find("impl Foo").unwrap()..];
+        let items = texts(
+            impl_block,
+            split_code_into_indices(impl_block, CodeLanguage::Rust, 1),
+        );
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], "impl Foo {");
+        assert!(items[1].starts_with("/// Creates a Foo.\n    pub fn new("));
+        assert!(items[1].ends_with("Self { a }\n    }"));
+        assert!(items[2].starts_with("fn get<'a>") && items[2].ends_with("}\n}"));
+    }
+
+    const PYTHON: &str = "import os\n\n\n@decorator\ndef foo(\n    a,\n):\n    \"\"\"Docstring.\n\ndef not_a_function():\n    \"\"\"\n    return a\n\n\nclass Bar:\n    # Comment.\n    def baz(self):\n        if True:\n            pass\n        else:\n            pass\n";
+
+    #[test]
+    fn python() {
+        let items = texts(
+            PYTHON,
+            split_code_into_indices(PYTHON, CodeLanguage::Python, 0),
+        );
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], "import os");
+        assert!(items[1].starts_with("@decorator\ndef foo(") && items[1].ends_with("return a"));
+        assert!(items[2].starts_with("class Bar:"));
+
+        let class_block = &PYTHON[PYTHON.find("class Bar").unwrap()..];
+        let items = texts(
+            class_block,
+            split_code_into_indices(class_block, CodeLanguage::Python, 1),
+        );
+        assert_eq!(items.len(), 2);
+        assert!(items[1].starts_with("# Comment.\n    def baz(self):"));
+        assert!(items[1].ends_with("pass"));
+    }
+}
@@ -0,0 +1,145 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Where the text matched by a [`CustomSeparator`] goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchPlacement {
+    /// The match starts the following split, like a heading such as `Article 12`.
+    #[default]
+    Before,
+    /// The match ends the preceding split, like the `;` that ends a clause.
+    After,
+    /// The match is in no split, like a `---` page break.
+    Drop,
+}
+
+/// A user-defined [`super::Separator`] that splits text on the matches of a regex, such as page breaks, numbered articles, or clauses.
+/// Regexes match text whose newlines are kept, so line anchors work with the `(?m)` flag, as in `(?m)^Article \d+`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "CustomSeparatorDef", into = "CustomSeparatorDef")]
+pub struct CustomSeparator {
+    /// Identifies the separator in reports and errors.
+    pub name: String,
+    pub placement: MatchPlacement,
+    regex: Regex,
+}
+
+impl CustomSeparator {
+    /// Splits before each match of `pattern`. See [`MatchPlacement`].
+    pub fn new<T: AsRef<str>>(name: T, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.as_ref().to_string(),
+            placement: MatchPlacement::default(),
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    /// Sets where the matched text goes. Default is [`MatchPlacement::Before`].
+    pub fn placement(mut self, placement: MatchPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// Splits text at each match. Empty matches are ignored.
+    pub(super) fn split_text_into_indices(&self, text: &str) -> Vec<Range<usize>> {
+        let mut split_indices = Vec::new();
+        let mut start = 0;
+        for m in self.regex.find_iter(text).filter(|m| !m.is_empty()) {
+            let (end, next_start) = match self.placement {
+                MatchPlacement::Before => (m.start(), m.start()),
+                MatchPlacement::After => (m.end(), m.end()),
+                MatchPlacement::Drop => (m.start(), m.end()),
+            };
+            if end > start {
+                split_indices.push(start..end);
+            }
+            start = next_start;
+        }
+        if start < text.len() {
+            split_indices.push(start..text.len());
+        }
+        split_indices
+    }
+}
+
+/// Separators are equal if they have the same name, pattern, and placement.
+impl PartialEq for CustomSeparator {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.placement == other.placement
+            && self.pattern() == other.pattern()
+    }
+}
+
+/// The serialized form of a [`CustomSeparator`], with the regex as its pattern.
+#[derive(Serialize, Deserialize)]
+struct CustomSeparatorDef {
+    name: String,
+    pattern: String,
+    placement: MatchPlacement,
+}
+
+impl From<CustomSeparator> for CustomSeparatorDef {
+    fn from(separator: CustomSeparator) -> Self {
+        Self {
+            pattern: separator.pattern().to_string(),
+            name: separator.name,
+            placement: separator.placement,
+        }
+    }
+}
+
+impl TryFrom<CustomSeparatorDef> for CustomSeparator {
+    type Error = regex::Error;
+
+    fn try_from(def: CustomSeparatorDef) -> Result<Self, Self::Error> {
+        Ok(Self::new(def.name, &def.pattern)?.placement(def.placement))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placements() {
+        let text = "Preamble.\nArticle 1 First.\nArticle 2 Second.";
+        let articles = CustomSeparator::new("article", r"(?m)^Article \d+").unwrap();
+        let splits: Vec<&str> = articles
+            .split_text_into_indices(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(splits, vec!["Preamble.\n", "Article 1 First.\n", "Article 2 Second."]);
+
+        let text = "one; two; three";
+        let clauses = CustomSeparator::new("clause", ";")
+            .unwrap()
+            .placement(MatchPlacement::After);
+        let splits: Vec<&str> = clauses
+            .split_text_into_indices(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(splits, vec!["one;", " two;", " three"]);
+
+        let text = "page one\n---\npage two";
+        let pages = CustomSeparator::new("page", r"\n---\n")
+            .unwrap()
+            .placement(MatchPlacement::Drop);
+        let splits: Vec<&str> = pages
+            .split_text_into_indices(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        assert_eq!(splits, vec!["page one", "page two"]);
+
+        let json = serde_json::to_string(&pages).unwrap();
+        assert_eq!(serde_json::from_str::<CustomSeparator>(&json).unwrap(), pages);
+    }
+}
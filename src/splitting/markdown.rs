@@ -0,0 +1,235 @@
+use std::ops::Range;
+
+/// Splits Markdown into sections that start at ATX headings of `level` or higher (e.g. level 2 splits on `#` and `##`).
+/// Text before the first heading is its own section. Headings inside fenced code blocks are ignored.
+pub fn split_markdown_headings_into_indices(text: &str, level: usize) -> Vec<Range<usize>> {
+    let mut section_starts: Vec<usize> = vec![0];
+    let mut fence = FenceState::default();
+    for (start, line) in lines_with_offsets(text) {
+        if !fence.is_open() {
+            if let Some(heading_level) = heading_level(line) {
+                if heading_level <= level && start != 0 {
+                    section_starts.push(start);
+                }
+            }
+        }
+        fence.update(line);
+    }
+    section_starts.push(text.len());
+    section_starts
+        .windows(2)
+        .map(|window| Range {
+            start: window[0],
+            end: window[1],
+        })
+        .collect()
+}
+
+/// Splits Markdown into blocks: paragraphs, top-level list items, fenced code blocks, and tables.
+/// Fenced code blocks are kept whole even when they contain blank lines, and a heading is kept with the block that follows it.
+pub fn split_markdown_blocks_into_indices(text: &str) -> Vec<Range<usize>> {
+    let mut blocks: Vec<Range<usize>> = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    // True while the current block holds only a heading, so it is joined with the next block.
+    let mut heading_only = false;
+    let mut fence = FenceState::default();
+
+    for (start, line) in lines_with_offsets(text) {
+        let end = start + line.len();
+        if fence.is_open() {
+            if let Some(block) = current.as_mut() {
+                block.end = end;
+            }
+            fence.update(line);
+            if !fence.is_open() {
+                blocks.extend(current.take());
+            }
+            continue;
+        }
+        let starts_block = if line.trim().is_empty() {
+            if !heading_only {
+                blocks.extend(current.take());
+            }
+            continue;
+        } else {
+            let marks_block = heading_level(line).is_some()
+                || FenceState::opening(line).is_some()
+                || is_list_item(line);
+            marks_block && !heading_only
+        };
+        if starts_block {
+            blocks.extend(current.take());
+        }
+        match current.as_mut() {
+            Some(block) => block.end = end,
+            None => current = Some(Range { start, end }),
+        }
+        heading_only = heading_level(line).is_some() && (starts_block || heading_only);
+        fence.update(line);
+    }
+    blocks.extend(current.take());
+    blocks
+}
+
+/// Finds the ATX headings of the text, returning the starting byte offset, level, and title of each. Headings inside fenced code blocks are ignored.
+pub fn markdown_headings(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut headings = Vec::new();
+    let mut fence = FenceState::default();
+    for (start, line) in lines_with_offsets(text) {
+        if !fence.is_open() {
+            if let Some(level) = heading_level(line) {
+                let title = strip_indent(line).unwrap_or(line)[level..].trim();
+                // An optional closing sequence of `#`s is not part of the title.
+                let title = match title.trim_end_matches('#') {
+                    trimmed if trimmed.is_empty() || trimmed.ends_with([' ', '\t']) => trimmed.trim_end(),
+                    _ => title,
+                };
+                headings.push((start, level, title));
+            }
+        }
+        fence.update(line);
+    }
+    headings
+}
+
+/// Iterates over the lines of the text, including their line endings, along with each line's starting byte offset.
+fn lines_with_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+/// Strips up to three spaces of indentation, which Markdown allows before block markers.
+fn strip_indent(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        None
+    } else {
+        Some(&line[indent..])
+    }
+}
+
+/// The level of an ATX heading line, e.g. `## Title` is level 2.
+fn heading_level(line: &str) -> Option<usize> {
+    let line = strip_indent(line)?;
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    match line[level..].chars().next() {
+        None | Some(' ') | Some('\t') | Some('\n') | Some('\r') => Some(level),
+        _ => None,
+    }
+}
+
+/// A top-level list item. Items indented by two or more spaces are treated as nested, and stay with their parent item.
+fn is_list_item(line: &str) -> bool {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 1 {
+        return false;
+    }
+    let line = &line[indent..];
+    let marker_len = if line.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 || digits > 9 || !line[digits..].starts_with(['.', ')']) {
+            return false;
+        }
+        digits + 1
+    };
+    matches!(line[marker_len..].chars().next(), Some(' ') | Some('\t'))
+}
+
+/// Tracks whether the scan is inside a fenced code block.
+#[derive(Default)]
+struct FenceState {
+    /// The fence character and length of the open fence.
+    open: Option<(char, usize)>,
+}
+
+impl FenceState {
+    fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    fn opening(line: &str) -> Option<(char, usize)> {
+        let line = strip_indent(line)?;
+        let fence_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+        let fence_len = line.len() - line.trim_start_matches(fence_char).len();
+        if fence_len >= 3 {
+            Some((fence_char, fence_len))
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, line: &str) {
+        match self.open {
+            Some((fence_char, fence_len)) => {
+                if let Some((closing_char, closing_len)) = Self::opening(line) {
+                    let rest = strip_indent(line).unwrap_or(line)[closing_len..].trim();
+                    if closing_char == fence_char && closing_len >= fence_len && rest.is_empty() {
+                        self.open = None;
+                    }
+                }
+            }
+            None => self.open = Self::opening(line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "# Title\n\nIntro paragraph.\n\n## Section one\n\nSome text.\n\n```rust\nfn main() {\n\n    # not a heading\n}\n```\n\n## Section two\n\n- item one\n  continued\n  - nested\n- item two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+
+    fn texts(text: &str, ranges: Vec<Range<usize>>) -> Vec<&str> {
+        ranges.into_iter().map(|range| text[range].trim()).collect()
+    }
+
+    #[test]
+    fn headings() {
+        let sections = texts(MARKDOWN, split_markdown_headings_into_indices(MARKDOWN, 1));
+        assert_eq!(sections.len(), 1);
+        let sections = texts(MARKDOWN, split_markdown_headings_into_indices(MARKDOWN, 2));
+        assert_eq!(sections.len(), 3);
+        assert!(sections[0].starts_with("# Title"));
+        assert!(sections[1].starts_with("## Section one"));
+        assert!(sections[1].contains("# not a heading"));
+        assert!(sections[2].starts_with("## Section two"));
+    }
+
+    #[test]
+    fn headings_titles() {
+        let titles: Vec<(usize, &str)> = markdown_headings(MARKDOWN)
+            .into_iter()
+            .map(|(_, level, title)| (level, title))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![(1, "Title"), (2, "Section one"), (2, "Section two")]
+        );
+        assert_eq!(markdown_headings("## Closed ##\n")[0].2, "Closed");
+        assert_eq!(markdown_headings("# C#\n")[0].2, "C#");
+    }
+
+    #[test]
+    fn blocks() {
+        let blocks = texts(MARKDOWN, split_markdown_blocks_into_indices(MARKDOWN));
+        assert_eq!(
+            blocks,
+            vec![
+                "# Title\n\nIntro paragraph.",
+                "## Section one\n\nSome text.",
+                "```rust\nfn main() {\n\n    # not a heading\n}\n```",
+                "## Section two\n\n- item one\n  continued\n  - nested",
+                "- item two",
+                "| a | b |\n|---|---|\n| 1 | 2 |",
+            ]
+        );
+    }
+}
@@ -1,6 +1,19 @@
+pub mod code;
+pub mod custom;
+pub mod markdown;
+pub mod protected;
 pub mod rule_based;
+pub mod table;
+use crate::clean_text::OffsetMap;
+pub use code::{split_code_into_indices, split_code_lines_into_indices, CodeLanguage};
+pub use custom::{CustomSeparator, MatchPlacement};
+pub use markdown::{
+    markdown_headings, split_markdown_blocks_into_indices, split_markdown_headings_into_indices,
+};
 use regex::Regex;
+pub use protected::{keep_spans_whole, ProtectedSpan, ProtectedSpans};
 pub use rule_based::split_text_into_indices;
+pub use table::{find_tables, keep_tables_whole, split_table_rows_into_indices, Table};
 use std::{
     collections::VecDeque,
     ops::Range,
@@ -12,6 +25,10 @@ pub struct TextSplitter {
     pub split_separator: Separator,
     pub recursive: bool,
     pub clean_text: bool,
+    /// Spans that no split divides. Default is None.
+    pub protected_spans: Option<Arc<ProtectedSpans>>,
+    /// The order separators are tried in when a text does not split. Default is None, which follows [`Separator::next`].
+    pub separators: Option<Arc<[Separator]>>,
 }
 
 impl TextSplitter {
@@ -20,6 +37,8 @@ impl TextSplitter {
             split_separator: Separator::TwoPlusEoL,
             recursive: true,
             clean_text: true,
+            protected_spans: None,
+            separators: None,
         }
     }
 
@@ -33,15 +52,15 @@ impl TextSplitter {
         let mut split_separator = self.split_separator.clone();
         let split_indices = if self.recursive {
             loop {
-                let split_indices = split_separator.split_text_into_indices(&base_text);
+                let split_indices = self.split_text_into_indices(&split_separator, &base_text);
                 if split_indices.len() > 1 {
                     break split_indices;
                 } else {
-                    split_separator = split_separator.next()?;
+                    split_separator = self.next_separator(&split_separator)?;
                 }
             }
         } else {
-            split_separator.split_text_into_indices(&base_text)
+            self.split_text_into_indices(&split_separator, &base_text)
         };
         if split_indices.len() < 2 {
             return None;
@@ -50,7 +69,7 @@ impl TextSplitter {
         Some(
             split_indices
                 .into_iter()
-                .map(|indices| TextSplit::new(&indices, &split_separator, &base_text))
+                .map(|indices| TextSplit::new(&indices, &split_separator, &base_text, self))
                 .collect(),
         )
     }
@@ -65,6 +84,12 @@ impl TextSplitter {
         self
     }
 
+    /// Splits tables into their rows, keeping each table's header rows together. Lines outside tables are split like [`TextSplitter::on_single_newline`].
+    pub fn on_table_rows(mut self) -> Self {
+        self.split_separator = Separator::TableRow;
+        self
+    }
+
     pub fn on_sentences_rule_based(mut self) -> Self {
         self.split_separator = Separator::SentencesRuleBased;
         self
@@ -85,6 +110,23 @@ impl TextSplitter {
         self
     }
 
+    /// Splits Markdown into sections on ATX headings of `level` or higher. `level` is clamped to 1..=6.
+    pub fn on_markdown_headings(mut self, level: usize) -> Self {
+        self.split_separator = Separator::MarkdownHeading(level.clamp(1, 6));
+        self
+    }
+
+    pub fn on_markdown_blocks(mut self) -> Self {
+        self.split_separator = Separator::MarkdownBlock;
+        self
+    }
+
+    /// Splits source code into its top-level items. Whitespace is never cleaned.
+    pub fn on_code(mut self, language: CodeLanguage) -> Self {
+        self.split_separator = Separator::Code { language, depth: 0 };
+        self
+    }
+
     pub fn on_separator(mut self, split_separator: &Separator) -> Self {
         self.split_separator = split_separator.clone();
         self
@@ -100,6 +142,46 @@ impl TextSplitter {
         self
     }
 
+    /// Never splits inside `protected_spans`. A separator that would divide a span leaves it whole within one split.
+    pub fn protected_spans(mut self, protected_spans: Arc<ProtectedSpans>) -> Self {
+        self.protected_spans = Some(protected_spans);
+        self
+    }
+
+    /// Splits on a user-defined hierarchy of separators, trying each in order and dropping repeats.
+    pub fn separators(mut self, separators: impl Into<Arc<[Separator]>>) -> Self {
+        let separators: Arc<[Separator]> = separators.into();
+        let mut unique: Vec<Separator> = Vec::with_capacity(separators.len());
+        for separator in separators.iter() {
+            if *separator != Separator::None && !unique.contains(separator) {
+                unique.push(separator.clone());
+            }
+        }
+        let separators: Arc<[Separator]> = unique.into();
+        if let Some(first) = separators.first() {
+            self.split_separator = first.clone();
+        }
+        self.separators = Some(separators);
+        self
+    }
+
+    /// The separator tried after `separator`. See [`Separator::next_in`].
+    fn next_separator(&self, separator: &Separator) -> Option<Separator> {
+        match &self.separators {
+            Some(separators) => separator.next_in(separators),
+            None => separator.next(),
+        }
+    }
+
+    /// Splits `text` on `separator`, keeping any protected spans whole.
+    fn split_text_into_indices(&self, separator: &Separator, text: &str) -> Vec<Range<usize>> {
+        let split_indices = separator.split_text_into_indices(text);
+        match &self.protected_spans {
+            Some(protected_spans) => protected_spans.keep_whole(text, split_indices),
+            None => split_indices,
+        }
+    }
+
     pub fn split_split(
         self,
         base_text: &Arc<str>,
@@ -110,11 +192,11 @@ impl TextSplitter {
 
         let mut split_separator = self.split_separator.clone();
         let split_indices = loop {
-            let split_indices = split_separator.split_text_into_indices(split_text);
+            let split_indices = self.split_text_into_indices(&split_separator, split_text);
             if split_indices.len() > 1 {
                 break split_indices;
             } else {
-                split_separator = split_separator.next()?;
+                split_separator = self.next_separator(&split_separator)?;
             }
         };
         Some(
@@ -123,7 +205,7 @@ impl TextSplitter {
                 .map(|indices| {
                     let start = start_offset + indices.start;
                     let end = start_offset + indices.end;
-                    TextSplit::new(&Range { start, end }, &split_separator, base_text)
+                    TextSplit::new(&Range { start, end }, &split_separator, base_text, &self)
                 })
                 .collect(),
         )
@@ -131,47 +213,56 @@ impl TextSplitter {
 
     pub fn splits_to_text(splits: &VecDeque<TextSplit>, with_seperator: bool) -> String {
         let mut text = String::new();
-        let mut last_separator = Separator::None;
         for (i, split) in splits.iter().enumerate() {
-            if last_separator == Separator::GraphemesUnicode
-                && split.split_separator != Separator::GraphemesUnicode
-            {
-                text.push(' ');
-            };
-            last_separator = split.split_separator.clone();
-            match split.split_separator {
-                Separator::TwoPlusEoL => {
-                    text.push_str(split.text());
-                    if with_seperator {
-                        text.push_str("\n\n");
-                    } else if i < splits.len() - 1 {
-                        text.push(' ');
-                    }
-                }
-                Separator::SingleEol => {
-                    text.push_str(split.text());
-                    if with_seperator {
-                        text.push('\n');
-                    } else if i < splits.len() - 1 {
-                        text.push(' ');
-                    }
-                }
-                Separator::SentencesRuleBased
-                | Separator::SentencesUnicode
-                | Separator::WordsUnicode => {
-                    text.push_str(split.text());
-                    if i < splits.len() - 1 {
-                        text.push(' ');
-                    }
-                }
-                Separator::GraphemesUnicode => {
-                    text.push_str(split.text());
-                }
-                Separator::None => unreachable!(),
+            text.push_str(split.text());
+            if let Some(next_split) = splits.get(i + 1) {
+                text.push_str(Self::joiner(split, next_split, with_seperator));
+            } else if with_seperator {
+                text.push_str(match split.split_separator {
+                    Separator::MarkdownHeading(_) | Separator::MarkdownBlock => "\n\n",
+                    Separator::Code { .. } | Separator::CodeLine => "\n",
+                    Separator::TwoPlusEoL => "\n\n",
+                    Separator::SingleEol | Separator::TableRow => "\n",
+                    _ => "",
+                });
             }
         }
         text
     }
+
+    /// The text placed between `split` and the following `next_split` when splits are joined with [`TextSplitter::splits_to_text`].
+    pub fn joiner<'a>(split: &'a TextSplit, next_split: &TextSplit, with_seperator: bool) -> &'a str {
+        // Table rows always start on their own line.
+        if next_split.split_separator == Separator::TableRow {
+            return split.whitespace_before(next_split).unwrap_or("\n");
+        }
+        match split.split_separator {
+            Separator::MarkdownHeading(_) | Separator::MarkdownBlock => {
+                split.whitespace_before(next_split).unwrap_or("\n\n")
+            }
+            Separator::Code { .. } | Separator::CodeLine | Separator::TableRow => {
+                split.whitespace_before(next_split).unwrap_or("\n")
+            }
+            // Only a dropped match leaves text other than whitespace between splits.
+            Separator::Custom(_) => split.whitespace_before(next_split).unwrap_or("\n\n"),
+            Separator::TwoPlusEoL if with_seperator => "\n\n",
+            Separator::SingleEol if with_seperator => "\n",
+            Separator::TwoPlusEoL
+            | Separator::SingleEol
+            | Separator::SentencesRuleBased
+            | Separator::SentencesUnicode
+            | Separator::WordsUnicode => " ",
+            // Graphemes are joined directly, but are separated from splits of other separators.
+            Separator::GraphemesUnicode => {
+                if next_split.split_separator == Separator::GraphemesUnicode {
+                    ""
+                } else {
+                    " "
+                }
+            }
+            Separator::None => unreachable!(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -180,16 +271,27 @@ pub struct TextSplit {
     pub split_separator: Separator,
     pub base_text: Arc<str>,
     pub token_count: Option<u32>,
+    /// The protected spans of the [`TextSplitter`] that made this split, kept by [`TextSplit::split`].
+    pub protected_spans: Option<Arc<ProtectedSpans>>,
+    /// The separator hierarchy of the [`TextSplitter`] that made this split, followed by [`TextSplit::split`].
+    pub separators: Option<Arc<[Separator]>>,
 }
 
 impl TextSplit {
-    fn new(indices: &Range<usize>, split_separator: &Separator, base_text: &Arc<str>) -> Self {
+    fn new(
+        indices: &Range<usize>,
+        split_separator: &Separator,
+        base_text: &Arc<str>,
+        splitter: &TextSplitter,
+    ) -> Self {
         Self {
             indices: indices.clone(),
             split_separator: split_separator.clone(),
             base_text: Arc::clone(base_text),
 
             token_count: None,
+            protected_spans: splitter.protected_spans.clone(),
+            separators: splitter.separators.clone(),
         }
     }
 
@@ -201,13 +303,44 @@ impl TextSplit {
         &self.base_text[self.indices.clone()]
     }
 
+    /// The whitespace between this split and the following split in the base text, if they are adjacent.
+    fn whitespace_before(&self, next_split: &TextSplit) -> Option<&str> {
+        let gap = self
+            .base_text
+            .get(self.indices.end..next_split.indices.start)?;
+        if gap.chars().all(char::is_whitespace) {
+            Some(gap)
+        } else {
+            None
+        }
+    }
+
+    /// Splits this split on the next separator, with the protected spans and separator hierarchy of the [`TextSplitter`] that made it.
     pub fn split(&self) -> Option<VecDeque<TextSplit>> {
-        TextSplitter::default()
-            .on_separator(&self.split_separator.next()?)
+        let splitter = TextSplitter {
+            protected_spans: self.protected_spans.clone(),
+            separators: self.separators.clone(),
+            ..Default::default()
+        };
+        splitter
+            .on_separator(&splitter.next_separator(&self.split_separator)?)
             .split_split(&self.base_text, &self.indices)
     }
 }
 
+/// Serializes the split's own text rather than the whole base text it points into.
+impl serde::Serialize for TextSplit {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("TextSplit", 4)?;
+        state.serialize_field("text", self.text())?;
+        state.serialize_field("indices", &self.indices)?;
+        state.serialize_field("split_separator", &self.split_separator)?;
+        state.serialize_field("token_count", &self.token_count)?;
+        state.end()
+    }
+}
+
 #[derive(PartialEq)]
 pub enum SeparatorGroup {
     Semantic,
@@ -219,6 +352,7 @@ impl SeparatorGroup {
             Self::Semantic => vec![
                 Separator::TwoPlusEoL,
                 Separator::SingleEol,
+                Separator::TableRow,
                 Separator::SentencesRuleBased,
                 Separator::SentencesUnicode,
             ],
@@ -227,15 +361,30 @@ impl SeparatorGroup {
     }
 }
 
-#[derive(PartialEq, Debug, Clone, Default)]
+#[derive(PartialEq, Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub enum Separator {
+    /// Markdown sections starting at ATX headings of the given level or higher.
+    MarkdownHeading(usize),
+    /// Markdown paragraphs, list items, fenced code blocks, and tables.
+    MarkdownBlock,
+    /// Source code items at the given block nesting depth, with their comments and attributes attached.
+    Code {
+        language: CodeLanguage,
+        depth: usize,
+    },
+    /// Single lines of source code, with indentation preserved.
+    CodeLine,
     #[default]
     TwoPlusEoL,
     SingleEol,
+    /// Table rows, with the header rows of each table kept together. Only used to split a table that does not fit in a chunk.
+    TableRow,
     SentencesRuleBased,
     SentencesUnicode,
     WordsUnicode,
     GraphemesUnicode,
+    /// A user-defined separator that splits on the matches of a regex. Use it in a hierarchy set with [`TextSplitter::separators`] or `TextChunker::separators`.
+    Custom(CustomSeparator),
     None,
 }
 
@@ -251,38 +400,106 @@ impl Separator {
         ]
     }
 
+    /// The separators used as initial separators when chunking Markdown.
+    /// Headings are preferred in order of level, then blocks. Code fences and tables are only split once every Markdown separator is exhausted.
+    pub fn get_all_markdown() -> Vec<Self> {
+        vec![
+            Self::MarkdownHeading(1),
+            Self::MarkdownHeading(2),
+            Self::MarkdownHeading(3),
+            Self::MarkdownHeading(4),
+            Self::MarkdownHeading(5),
+            Self::MarkdownHeading(6),
+            Self::MarkdownBlock,
+        ]
+    }
+
+    /// The separators used as initial separators when chunking source code.
+    /// Top-level items are preferred, then items in successively deeper blocks, and finally single lines.
+    pub fn get_all_code(language: CodeLanguage) -> Vec<Self> {
+        let mut separators: Vec<Self> = (0..=code::CODE_DEPTH_MAX)
+            .map(|depth| Self::Code { language, depth })
+            .collect();
+        separators.push(Self::CodeLine);
+        separators
+    }
+
     pub fn group(&self) -> SeparatorGroup {
         match self {
-            Self::TwoPlusEoL
+            Self::MarkdownHeading(_)
+            | Self::MarkdownBlock
+            | Self::Code { .. }
+            | Self::CodeLine
+            | Self::TwoPlusEoL
             | Self::SingleEol
+            | Self::TableRow
             | Self::SentencesRuleBased
-            | Self::SentencesUnicode => SeparatorGroup::Semantic,
+            | Self::SentencesUnicode
+            | Self::Custom(_) => SeparatorGroup::Semantic,
             Self::WordsUnicode | Self::GraphemesUnicode => SeparatorGroup::Syntactic,
             Self::None => unreachable!(),
         }
     }
 
     pub fn clean_text(&self, text: &str) -> String {
-        match self {
-            Self::TwoPlusEoL => crate::TextCleaner::new()
-                .reduce_newlines_to_double_newline()
-                .run(text),
-            Self::SingleEol => crate::TextCleaner::new()
-                .reduce_newlines_to_single_newline()
-                .run(text),
+        match self.text_cleaner() {
+            Some(text_cleaner) => text_cleaner.run(text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Cleans the text for this separator, and returns an [`OffsetMap`] from the cleaned text back to the incoming text.
+    pub fn clean_text_with_offset_map(&self, text: &str) -> (String, OffsetMap) {
+        match self.text_cleaner() {
+            Some(text_cleaner) => text_cleaner.run_with_offset_map(text),
+            None => (text.to_string(), OffsetMap::identity(text.len())),
+        }
+    }
+
+    /// The cleaner for this separator. Source code is never cleaned, so that its whitespace is preserved exactly.
+    fn text_cleaner(&self) -> Option<crate::TextCleaner> {
+        let text_cleaner = match self {
+            Self::Code { .. } | Self::CodeLine => return None,
+            // Custom separators keep newlines, so their regexes can match line structure.
+            Self::MarkdownHeading(_) | Self::MarkdownBlock | Self::Custom(_) => {
+                crate::TextCleaner::new()
+                    .do_not_reduce_newlines()
+                    .keep_whitespace()
+            }
+            Self::TwoPlusEoL => crate::TextCleaner::new().reduce_newlines_to_double_newline(),
+            Self::SingleEol | Self::TableRow => {
+                crate::TextCleaner::new().reduce_newlines_to_single_newline()
+            }
             Self::SentencesRuleBased
             | Self::SentencesUnicode
             | Self::WordsUnicode
-            | Self::GraphemesUnicode => crate::TextCleaner::new()
-                .reduce_newlines_to_single_space()
-                .run(text),
+            | Self::GraphemesUnicode => crate::TextCleaner::new().reduce_newlines_to_single_space(),
             Self::None => unreachable!(),
-        }
+        };
+        Some(text_cleaner)
     }
 
     pub fn split_text_into_indices<T: AsRef<str>>(&self, text: T) -> Vec<Range<usize>> {
         let mut split_indices: Vec<Range<usize>> = Vec::new();
         match self {
+            Self::MarkdownHeading(level) => {
+                split_indices = split_markdown_headings_into_indices(text.as_ref(), *level);
+            }
+            Self::MarkdownBlock => {
+                split_indices = split_markdown_blocks_into_indices(text.as_ref());
+            }
+            Self::Code { language, depth } => {
+                split_indices = split_code_into_indices(text.as_ref(), *language, *depth);
+            }
+            Self::CodeLine => {
+                split_indices = split_code_lines_into_indices(text.as_ref());
+            }
+            Self::TableRow => {
+                split_indices = split_table_rows_into_indices(text.as_ref());
+            }
+            Self::Custom(separator) => {
+                split_indices = separator.split_text_into_indices(text.as_ref());
+            }
             Self::TwoPlusEoL | Self::SingleEol => {
                 let pattern_matches = match self {
                     Self::TwoPlusEoL => TWO_PLUS_NEWLINE_REGEX.find_iter(text.as_ref()),
@@ -349,29 +566,91 @@ impl Separator {
             }
             Self::None => unreachable!(),
         }
-        split_indices
+        let split_indices = split_indices
             .into_iter()
             .filter_map(|indices| self.trim_range(&indices, text.as_ref()))
-            .collect()
+            .collect();
+        match self {
+            // Tables are kept whole until they are split on their rows. Words and graphemes are the last resort, and may divide anything.
+            Self::TwoPlusEoL
+            | Self::SingleEol
+            | Self::SentencesRuleBased
+            | Self::SentencesUnicode
+            | Self::Custom(_) => keep_tables_whole(text.as_ref(), split_indices),
+            _ => split_indices,
+        }
     }
 
     pub fn next(&self) -> Option<Self> {
         match self {
+            Self::MarkdownHeading(level) if *level < 6 => Some(Self::MarkdownHeading(level + 1)),
+            Self::MarkdownHeading(_) => Some(Self::MarkdownBlock),
+            Self::MarkdownBlock => Some(Self::SingleEol),
+            Self::Code { language, depth } if *depth < code::CODE_DEPTH_MAX => Some(Self::Code {
+                language: *language,
+                depth: depth + 1,
+            }),
+            Self::Code { .. } => Some(Self::CodeLine),
+            Self::CodeLine => Some(Self::WordsUnicode),
             Self::TwoPlusEoL => Some(Self::SingleEol),
-            Self::SingleEol => Some(Self::SentencesRuleBased),
+            Self::SingleEol => Some(Self::TableRow),
+            Self::TableRow => Some(Self::SentencesRuleBased),
             Self::SentencesRuleBased => Some(Self::SentencesUnicode),
             Self::SentencesUnicode => Some(Self::WordsUnicode),
             Self::WordsUnicode => Some(Self::GraphemesUnicode),
             Self::GraphemesUnicode => None,
+            // Outside a hierarchy, a custom separator falls back to the plain text separators.
+            Self::Custom(_) => Some(Self::TwoPlusEoL),
             Self::None => unreachable!(),
         }
     }
+
+    /// The separator after this one in `hierarchy`, then the built-in [`Separator::next`] separators that are not in it.
+    pub fn next_in(&self, hierarchy: &[Separator]) -> Option<Self> {
+        if let Some(i) = hierarchy.iter().position(|separator| separator == self) {
+            // Repeats of an earlier separator are skipped, so no separator is tried twice.
+            if let Some(next) = hierarchy[i + 1..]
+                .iter()
+                .find(|separator| !hierarchy[..=i].contains(separator))
+            {
+                return Some(next.clone());
+            }
+        }
+        let mut next = self.next()?;
+        while hierarchy.contains(&next) {
+            next = next.next()?;
+        }
+        Some(next)
+    }
     fn trim_range<T: AsRef<str>>(&self, indices: &Range<usize>, text: T) -> Option<Range<usize>> {
         let (start, end) = match self {
-            Self::TwoPlusEoL
+            Self::Code { .. } | Self::CodeLine => {
+                // Keeps the indentation of the first line, and drops leading blank lines and trailing whitespace.
+                let first_content = text.as_ref()[indices.start..indices.end]
+                    .char_indices()
+                    .find(|(_, c)| !c.is_whitespace())
+                    .map(|(i, _)| indices.start + i)
+                    .unwrap_or(indices.end);
+                let start = text.as_ref()[indices.start..first_content]
+                    .rfind('\n')
+                    .map(|i| indices.start + i + 1)
+                    .unwrap_or(indices.start);
+                let end = text.as_ref()[indices.start..indices.end]
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| !c.is_whitespace())
+                    .map(|(i, c)| indices.start + i + c.len_utf8())
+                    .unwrap_or(start);
+                (start, end)
+            }
+            Self::MarkdownHeading(_)
+            | Self::MarkdownBlock
+            | Self::TwoPlusEoL
             | Self::SingleEol
+            | Self::TableRow
             | Self::SentencesRuleBased
-            | Self::SentencesUnicode => {
+            | Self::SentencesUnicode
+            | Self::Custom(_) => {
                 let start = text.as_ref()[indices.start..indices.end]
                     .char_indices()
                     .find(|(_, c)| !c.is_whitespace())
@@ -636,6 +915,60 @@ mod tests {
         counts(Separator::WordsUnicode, &TEXT.medium.content, 3847).unwrap();
     }
 
+    #[test]
+    fn test_markdown_recursive() {
+        let markdown = "# One\n\nFirst paragraph.\n\n- item\n- item\n\n# Two\n\nSecond paragraph.";
+        let res = TextSplitter::new()
+            .on_markdown_headings(1)
+            .split_text(markdown)
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].split_separator, Separator::MarkdownHeading(1));
+        let res = res[0].split().unwrap();
+        assert_eq!(res[0].split_separator, Separator::MarkdownBlock);
+        assert_eq!(res[0].text(), "# One\n\nFirst paragraph.");
+        assert_eq!(
+            TextSplitter::splits_to_text(&res, false),
+            "# One\n\nFirst paragraph.\n\n- item\n- item"
+        );
+    }
+
+    #[test]
+    fn test_code_recursive() {
+        let code = "fn one() {\n    let a = 1;\n\n    let b = 2;\n}\n\n\nfn two() {}\n";
+        let res = TextSplitter::new()
+            .on_code(CodeLanguage::Rust)
+            .split_text(code)
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        let res = res[0].split().unwrap();
+        assert_eq!(
+            res[0].split_separator,
+            Separator::Code {
+                language: CodeLanguage::Rust,
+                depth: 1
+            }
+        );
+        assert_eq!(res[1].text(), "    let a = 1;");
+        assert_eq!(
+            TextSplitter::splits_to_text(&res, false),
+            "fn one() {\n    let a = 1;\n\n    let b = 2;\n}"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_separators() {
+        let pages = Separator::Custom(CustomSeparator::new("page", r"\f").unwrap());
+        let splitter = TextSplitter::new().separators(vec![pages.clone(), pages.clone()]);
+        assert_eq!(splitter.separators.as_deref(), Some(&[pages.clone()][..]));
+        let res = splitter.split_text("one two").unwrap();
+        assert_eq!(res[0].split_separator, Separator::WordsUnicode);
+        assert_eq!(
+            pages.next_in(&[pages.clone(), pages.clone(), Separator::SingleEol]),
+            Some(Separator::SingleEol)
+        );
+    }
+
     #[test]
     fn test_graphemes_indices() {
         matches(
@@ -0,0 +1,214 @@
+use crate::extract::{extract_email_ranges, extract_url_ranges};
+use regex::Regex;
+use std::{ops::Range, sync::LazyLock};
+
+/// Code between single backticks on one line.
+static INLINE_CODE_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`[^`\n]+`").unwrap());
+/// Display and inline LaTeX math: `$$...$$`, `\[...\]`, `\(...\)`, and `$...$` on one line.
+static LATEX_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$\$[^$]+\$\$|\\\[[\s\S]+?\\\]|\\\([\s\S]+?\\\)|\$[^$\n]+\$").unwrap()
+});
+/// Passages in straight or curly double quotes. Bounded, so a stray quote mark can not protect the rest of the text.
+static QUOTE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""[^"]{1,1000}"|“[^“”]{1,1000}”"#).unwrap());
+
+/// A kind of text that splits never divide. See [`ProtectedSpans`].
+#[derive(Clone, Debug)]
+pub enum ProtectedSpan {
+    /// URLs, found with [`crate::extract::extract_url_ranges`].
+    Urls,
+    /// E-mail addresses.
+    Emails,
+    /// Inline code between single backticks.
+    InlineCode,
+    /// LaTeX math between `$`, `$$`, `\(` and `\)`, or `\[` and `\]`.
+    Latex,
+    /// Passages of up to 1000 bytes in double quotes.
+    Quotes,
+    /// Every match of a custom regex, such as `\{\{[^}]+\}\}` for `{{placeholder}}` markers.
+    Pattern(Regex),
+}
+
+impl ProtectedSpan {
+    /// The byte ranges of the spans in `text`.
+    fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let regex = match self {
+            Self::Urls => return extract_url_ranges(text),
+            Self::Emails => return extract_email_ranges(text),
+            Self::InlineCode => &*INLINE_CODE_REGEX,
+            Self::Latex => &*LATEX_REGEX,
+            Self::Quotes => &*QUOTE_REGEX,
+            Self::Pattern(regex) => regex,
+        };
+        regex.find_iter(text).map(|m| m.range()).collect()
+    }
+}
+
+/// Spans of text that split indices never fall inside, such as URLs, inline code, or template markers.
+/// A split that would divide a span is merged with its neighbors until the span is whole, so a span is only ever part of one split.
+/// A span larger than a chunk can therefore not be chunked, which fails the chunking attempt like any other text that can not be split further.
+///
+/// ```ignore
+/// let spans = ProtectedSpans::new()
+///     .urls()
+///     .inline_code()
+///     .pattern(r"\{\{[^}]+\}\}")?;
+/// let chunker = TextChunker::new()?.protected_spans(spans);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProtectedSpans {
+    pub spans: Vec<ProtectedSpan>,
+}
+
+impl ProtectedSpans {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Protects URLs, e-mail addresses, inline code, and LaTeX math. Quotes are left out, because quoted passages can be long.
+    pub fn builtins() -> Self {
+        Self::new().urls().emails().inline_code().latex()
+    }
+
+    pub fn urls(self) -> Self {
+        self.add(ProtectedSpan::Urls)
+    }
+
+    pub fn emails(self) -> Self {
+        self.add(ProtectedSpan::Emails)
+    }
+
+    pub fn inline_code(self) -> Self {
+        self.add(ProtectedSpan::InlineCode)
+    }
+
+    pub fn latex(self) -> Self {
+        self.add(ProtectedSpan::Latex)
+    }
+
+    pub fn quotes(self) -> Self {
+        self.add(ProtectedSpan::Quotes)
+    }
+
+    /// Protects every match of `pattern`.
+    pub fn pattern(self, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(self.add(ProtectedSpan::Pattern(Regex::new(pattern)?)))
+    }
+
+    pub fn add(mut self, span: ProtectedSpan) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// The byte ranges of every protected span in `text`, in order. Overlapping spans are merged into one.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> =
+            self.spans.iter().flat_map(|span| span.find(text)).collect();
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+
+    /// Merges the splits of `text` that divide a protected span. See [`keep_spans_whole`].
+    pub fn keep_whole(&self, text: &str, split_indices: Vec<Range<usize>>) -> Vec<Range<usize>> {
+        if self.is_empty() {
+            return split_indices;
+        }
+        keep_spans_whole(&self.find(text), split_indices)
+    }
+}
+
+/// Merges the splits that divide any of `spans` into a single split, so every span is within one split.
+/// Both `spans` and `split_indices` must be in order, and `spans` must not overlap.
+pub fn keep_spans_whole(
+    spans: &[Range<usize>],
+    split_indices: Vec<Range<usize>>,
+) -> Vec<Range<usize>> {
+    if spans.is_empty() {
+        return split_indices;
+    }
+    let mut spans = spans.iter().peekable();
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(split_indices.len());
+    for mut split in split_indices {
+        // Spans that end before this split can not divide it or any later split.
+        while spans.next_if(|span| span.end <= split.start).is_some() {}
+        while let Some(span) = spans.next_if(|span| span.start < split.end) {
+            split.start = split.start.min(span.start);
+            split.end = split.end.max(span.end);
+        }
+        match merged.last_mut() {
+            Some(last) if split.start < last.end => last.end = last.end.max(split.end),
+            _ => merged.push(split),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splitting::{Separator, TextSplitter};
+    use std::sync::Arc;
+
+    #[test]
+    fn spans() {
+        let text = "See https://example.com/a/b.html or mail bob@example.com, run `cargo test --all`, and solve $x = y + 1$ for {{name}}.";
+        let spans = ProtectedSpans::builtins().pattern(r"\{\{[^}]+\}\}").unwrap();
+        let found: Vec<&str> = spans.find(text).into_iter().map(|range| &text[range]).collect();
+        assert_eq!(
+            found,
+            vec![
+                "https://example.com/a/b.html",
+                "bob@example.com",
+                "`cargo test --all`",
+                "$x = y + 1$",
+                "{{name}}",
+            ]
+        );
+
+        for separator in [Separator::WordsUnicode, Separator::GraphemesUnicode] {
+            let splits = spans.keep_whole(text, separator.split_text_into_indices(text));
+            for span in spans.find(text) {
+                assert!(
+                    splits.iter().any(|split| split.start <= span.start && span.end <= split.end),
+                    "{:?} divides {:?}",
+                    separator,
+                    &text[span]
+                );
+            }
+        }
+        // A span covering several splits merges them, and splits after it are untouched.
+        assert_eq!(
+            keep_spans_whole(&[2..7, 9..10], vec![0..3, 3..6, 6..8, 8..9, 9..12]),
+            vec![0..8, 8..9, 9..12]
+        );
+        // Splits of splits keep the spans of the splitter that made them.
+        let splits = TextSplitter::new()
+            .on_sentences_rule_based()
+            .protected_spans(Arc::new(ProtectedSpans::new().urls()))
+            .split_text("Read https://example.com/a/b.html today. Then stop.")
+            .unwrap();
+        assert!(splits[0]
+            .split()
+            .unwrap()
+            .iter()
+            .any(|split| split.text().contains("https://example.com/a/b.html")));
+        let quoted = "He said \"stop right there, please\" and left.";
+        let splits = ProtectedSpans::new()
+            .quotes()
+            .keep_whole(quoted, Separator::WordsUnicode.split_text_into_indices(quoted));
+        assert!(splits
+            .iter()
+            .any(|split| quoted[split.clone()].contains("\"stop right there, please\"")));
+    }
+}
@@ -18,7 +18,8 @@ pub fn split_text_into_indices(text: &str, keep_separator: bool) -> Vec<Range<us
     if !indices.is_empty() {
         let last = indices.last().unwrap();
         if last.end != text.len() {
-            eprintln!("split_text_into_indices: indices do not align with input text.");
+            #[cfg(feature = "tracing")]
+            tracing::warn!("split_text_into_indices: indices do not align with input text.");
             return vec![];
         }
     }
@@ -0,0 +1,208 @@
+use super::protected::keep_spans_whole;
+use regex::Regex;
+use std::{ops::Range, sync::LazyLock};
+
+/// The delimiter row under a Markdown pipe table's header, such as `| --- | :---: |`.
+static TABLE_DELIMITER_ROW: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[ \t]*\|?[ \t]*:?-+:?[ \t]*(\|[ \t]*:?-+:?[ \t]*)*\|?[ \t]*\r?$").unwrap()
+});
+/// The fewest rows, header included, of a tab or comma delimited block that is treated as a table.
+const DELIMITED_TABLE_MIN_ROWS: usize = 3;
+
+/// A table found by [`find_tables`]. All ranges are byte ranges in the searched text, and exclude line endings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// The whole table, from the start of the header to the end of the last row.
+    pub range: Range<usize>,
+    /// The header rows: the header and delimiter rows of a Markdown table, or the first row of a delimited table.
+    pub header: Range<usize>,
+    /// Each row after the header.
+    pub rows: Vec<Range<usize>>,
+}
+
+/// Finds Markdown pipe tables, and blocks of at least three lines with the same number of tab or comma separated cells.
+/// Comma separated blocks also need a column whose cells are all numbers or quoted below the header, so lists such as `Smith, John` are not tables.
+/// Tab delimited tables are only found in text whose tabs were kept, because cleaning reduces tabs to spaces.
+pub fn find_tables(text: &str) -> Vec<Table> {
+    let lines: Vec<Range<usize>> = text
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some(start..start + line.trim_end_matches(['\n', '\r']).len())
+        })
+        .collect();
+    let line = |i: usize| &text[lines[i].clone()];
+
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if i + 1 < lines.len()
+            && line(i).contains('|')
+            && line(i + 1).contains('|')
+            && TABLE_DELIMITER_ROW.is_match(line(i + 1))
+        {
+            let mut end = i + 2;
+            while end < lines.len() && line(end).contains('|') && !line(end).trim().is_empty() {
+                end += 1;
+            }
+            tables.push(Table {
+                range: lines[i].start..lines[end - 1].end,
+                header: lines[i].start..lines[i + 1].end,
+                rows: lines[i + 2..end].to_vec(),
+            });
+            i = end;
+            continue;
+        }
+        let delimited = ['\t', ','].into_iter().find_map(|delimiter| {
+            let cells = delimited_cells(line(i), delimiter);
+            if cells < 2 {
+                return None;
+            }
+            let mut end = i + 1;
+            while end < lines.len() && delimited_cells(line(end), delimiter) == cells {
+                end += 1;
+            }
+            (end - i >= DELIMITED_TABLE_MIN_ROWS
+                && (delimiter != ',' || has_typed_column(&lines[i + 1..end], text, cells)))
+            .then_some(end)
+        });
+        match delimited {
+            Some(end) => {
+                tables.push(Table {
+                    range: lines[i].start..lines[end - 1].end,
+                    header: lines[i].clone(),
+                    rows: lines[i + 1..end].to_vec(),
+                });
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+    tables
+}
+
+/// The number of cells in a delimited row, or 0 if the line does not look like a row.
+/// Comma separated prose is ruled out by cells that end or contain a sentence.
+fn delimited_cells(line: &str, delimiter: char) -> usize {
+    if line.trim().is_empty() || !line.contains(delimiter) {
+        return 0;
+    }
+    let cells: Vec<&str> = line.split(delimiter).collect();
+    if delimiter == ','
+        && cells
+            .iter()
+            .any(|cell| cell.trim_end().ends_with(['.', '!', '?']) || cell.contains(". "))
+    {
+        return 0;
+    }
+    cells.len()
+}
+
+/// Whether a column of comma separated `rows` with `cells` cells each has only numbers or quoted cells.
+fn has_typed_column(rows: &[Range<usize>], text: &str, cells: usize) -> bool {
+    let rows: Vec<Vec<&str>> = rows
+        .iter()
+        .map(|row| text[row.clone()].split(',').map(str::trim).collect())
+        .collect();
+    (0..cells).any(|column| {
+        rows.iter().all(|row| {
+            let cell = row[column];
+            cell.parse::<f64>().is_ok()
+                || (cell.len() >= 2 && cell.starts_with('"') && cell.ends_with('"'))
+        })
+    })
+}
+
+/// Splits text into table rows: the header rows of each table are one split, and each following row is a split. Lines outside tables are each a split.
+pub fn split_table_rows_into_indices(text: &str) -> Vec<Range<usize>> {
+    let mut split_indices = Vec::new();
+    let mut last_end = 0;
+    let push_lines = |split_indices: &mut Vec<Range<usize>>, range: Range<usize>| {
+        let mut start = range.start;
+        for line in text[range].split_inclusive('\n') {
+            if !line.trim().is_empty() {
+                split_indices.push(start..start + line.len());
+            }
+            start += line.len();
+        }
+    };
+    for table in find_tables(text) {
+        push_lines(&mut split_indices, last_end..table.header.start);
+        split_indices.push(table.header);
+        split_indices.extend(table.rows);
+        last_end = table.range.end;
+    }
+    push_lines(&mut split_indices, last_end..text.len());
+    split_indices
+}
+
+/// Merges the splits that divide a table into a single split, so a table is only divided once it is split on its rows.
+pub fn keep_tables_whole(text: &str, split_indices: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    // A table has at least two lines.
+    if !text.contains('\n') {
+        return split_indices;
+    }
+    let tables: Vec<Range<usize>> = find_tables(text).into_iter().map(|table| table.range).collect();
+    keep_spans_whole(&tables, split_indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Intro line.\n| Name | Value |\n| --- | ---: |\n| a | 1 |\n| b | 2 |\nBetween, tables.\nid,name,score\n1,ann,3\n2,bob,4\nOutro, with a comma.\nAnother, with a comma.\nA third, with a comma.\n";
+
+    fn texts(text: &str, ranges: Vec<Range<usize>>) -> Vec<&str> {
+        ranges.into_iter().map(|range| text[range].trim()).collect()
+    }
+
+    #[test]
+    fn tables() {
+        let tables = find_tables(TEXT);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(&TEXT[tables[0].header.clone()], "| Name | Value |\n| --- | ---: |");
+        assert_eq!(texts(TEXT, tables[0].rows.clone()), vec!["| a | 1 |", "| b | 2 |"]);
+        assert_eq!(&TEXT[tables[1].header.clone()], "id,name,score");
+        assert_eq!(&TEXT[tables[1].range.clone()], "id,name,score\n1,ann,3\n2,bob,4");
+
+        let tsv = "x\ty\n1\t2\n3\t4\n";
+        assert_eq!(find_tables(tsv)[0].rows.len(), 2);
+        // Too few rows, or a thematic break rather than a delimiter row.
+        assert!(find_tables("a,b\n1,2\n").is_empty());
+        assert!(find_tables("Some text\n---\nMore text\n").is_empty());
+        // Comma separated lists without a column of numbers or quoted cells.
+        assert!(find_tables("Smith, John\nDoe, Jane\nRoe, Richard\n").is_empty());
+        assert!(find_tables("red, green, blue\ncats, dogs, birds\noak, elm, ash\n").is_empty());
+        assert_eq!(
+            find_tables("name,city\n\"Ann\",\"Oslo\"\n\"Bob\",\"Rome\"\n").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn rows_and_whole_tables() {
+        let rows = texts(TEXT, split_table_rows_into_indices(TEXT));
+        assert_eq!(rows[0], "Intro line.");
+        assert_eq!(rows[1], "| Name | Value |\n| --- | ---: |");
+        assert_eq!(rows[2], "| a | 1 |");
+        assert_eq!(rows[5], "id,name,score");
+        assert_eq!(rows.last().unwrap(), &"A third, with a comma.");
+
+        let lines: Vec<Range<usize>> = TEXT
+            .split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some(start..*offset)
+            })
+            .collect();
+        let whole = texts(TEXT, keep_tables_whole(TEXT, lines));
+        assert_eq!(
+            whole[1],
+            "| Name | Value |\n| --- | ---: |\n| a | 1 |\n| b | 2 |"
+        );
+        assert_eq!(whole[3], "id,name,score\n1,ann,3\n2,bob,4");
+        assert_eq!(whole.len(), 7);
+    }
+}